@@ -0,0 +1,45 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Exposes the adi-core version this build resolved as `ADI_CORE_VERSION`,
+//! read from the workspace's Cargo.lock, for `/version` and the index marker.
+
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    // The lock sits at the workspace root, above this package
+    let lock = Path::new(&manifest_dir)
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file());
+    let version = lock.and_then(|path| {
+        println!("cargo:rerun-if-changed={}", path.display());
+        adi_core_version(&std::fs::read_to_string(path).ok()?)
+    });
+
+    println!(
+        "cargo:rustc-env=ADI_CORE_VERSION={}",
+        version.as_deref().unwrap_or("unknown")
+    );
+}
+
+/// Version of the `adi-core` package in a Cargo.lock.
+fn adi_core_version(lock: &str) -> Option<String> {
+    let mut in_adi_core = false;
+    for line in lock.lines().map(str::trim) {
+        if line == "[[package]]" {
+            in_adi_core = false;
+        } else if let Some(name) = line.strip_prefix("name = ") {
+            in_adi_core = name.trim_matches('"') == "adi-core";
+        } else if let Some(version) = line.strip_prefix("version = ") {
+            if in_adi_core {
+                return Some(version.trim_matches('"').to_string());
+            }
+        }
+    }
+    None
+}
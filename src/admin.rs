@@ -0,0 +1,256 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Operator routes under `/admin`, gated independently of the read API.
+
+use crate::{indexing, preview, upload, AppState};
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// How long a finished run is replayed for its `Idempotency-Key`.
+const IDEMPOTENCY_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Index runs by `Idempotency-Key`, so retried requests share one run.
+#[derive(Default)]
+pub struct IdempotencyKeys {
+    runs: Mutex<HashMap<String, Arc<KeyedRun>>>,
+}
+
+struct KeyedRun {
+    created: Instant,
+    response: OnceCell<(StatusCode, serde_json::Value)>,
+}
+
+impl IdempotencyKeys {
+    /// The run registered for `key`, and whether it was already there.
+    fn entry(&self, key: &str) -> (Arc<KeyedRun>, bool) {
+        let mut runs = self.runs.lock().unwrap();
+        runs.retain(|_, run| run.created.elapsed() < IDEMPOTENCY_TTL);
+
+        match runs.get(key) {
+            Some(run) => (run.clone(), true),
+            None => {
+                let run = Arc::new(KeyedRun {
+                    created: Instant::now(),
+                    response: OnceCell::new(),
+                });
+                runs.insert(key.to_string(), run.clone());
+                (run, false)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct IndexQuery {
+    /// Report what would be indexed without parsing or writing anything
+    #[serde(default)]
+    dry_run: bool,
+}
+
+pub fn router(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    if state.config.admin_token.is_none() && !state.config.read_only {
+        tracing::warn!("ADMIN_TOKEN is not set; /admin routes are open to anyone");
+    }
+
+    Router::new()
+        .route("/index", post(index_project))
+        .route("/index/errors", get(index_errors))
+        .route(
+            "/index/upload",
+            post(upload_project).layer(DefaultBodyLimit::max(state.config.max_upload_bytes)),
+        )
+        .route_layer(middleware::from_fn_with_state(state, require_admin))
+}
+
+async fn require_admin(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.config.read_only {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Admin API is disabled (READ_ONLY)" })),
+        )
+            .into_response();
+    }
+
+    if let Some(expected) = &state.config.admin_token {
+        let provided = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+
+        if !provided.is_some_and(|token| constant_time_eq(token.as_bytes(), expected.as_bytes())) {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                Json(serde_json::json!({ "error": "Missing or invalid admin token" })),
+            )
+                .into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn index_project(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<IndexQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if query.dry_run {
+        let project_path = state.project_path();
+        return match tokio::task::spawn_blocking(move || preview::preview(&project_path)).await {
+            Ok(preview) => {
+                let mut body = serde_json::to_value(preview).unwrap();
+                body["dry_run"] = true.into();
+                // The rules are this server's, not adi_core's; see `preview`
+                body["approximate"] = true.into();
+                (StatusCode::OK, Json(body)).into_response()
+            }
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response(),
+        };
+    }
+
+    let key = headers
+        .get("idempotency-key")
+        .and_then(|v| v.to_str().ok())
+        .filter(|k| !k.is_empty());
+    let Some(key) = key else {
+        let (status, body) = index_response(&state).await;
+        return (status, Json(body)).into_response();
+    };
+
+    // Retries wait for the first request's run and get its response; a
+    // refusal isn't kept, so a retry can still start the run
+    let (run, replayed) = state.idempotency.entry(key);
+    let ran = run.response.get_or_try_init(|| async {
+        let (status, body) = index_response(&state).await;
+        match status {
+            StatusCode::CONFLICT => Err((status, body)),
+            _ => Ok((status, body)),
+        }
+    });
+    let (status, body) = match ran.await {
+        Ok(response) => response.clone(),
+        Err((status, body)) => return (status, Json(body)).into_response(),
+    };
+
+    let mut response = (status, Json(body)).into_response();
+    if replayed {
+        response
+            .headers_mut()
+            .insert("idempotent-replayed", HeaderValue::from_static("true"));
+    }
+    response
+}
+
+async fn index_response(state: &AppState) -> (StatusCode, serde_json::Value) {
+    job_response(state, indexing::try_run_job(state).await)
+}
+
+fn job_response(
+    state: &AppState,
+    job: Result<(u64, anyhow::Result<adi_core::IndexProgress>), Option<indexing::JobReport>>,
+) -> (StatusCode, serde_json::Value) {
+    let job = match job {
+        Ok(job) => job,
+        Err(running) => {
+            return (
+                StatusCode::CONFLICT,
+                serde_json::json!({
+                    "error": "An index run is already in progress",
+                    "job_id": running.as_ref().map(|job| job.job_id),
+                    "indexing": running,
+                }),
+            )
+        }
+    };
+    match job {
+        (job_id, Ok(progress)) => {
+            let mut body = serde_json::to_value(progress).unwrap();
+            body["job_id"] = job_id.into();
+            let throughput = state.jobs.last_throughput();
+            // A newer run may already have replaced it
+            if let Some(throughput) = throughput.filter(|t| t.job_id == job_id) {
+                body["throughput"] = serde_json::to_value(throughput).unwrap();
+            }
+            (StatusCode::OK, body)
+        }
+        (job_id, Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            serde_json::json!({ "error": e.to_string(), "job_id": job_id }),
+        ),
+    }
+}
+
+/// Indexes an uploaded `tar.gz` or `zip` snapshot and serves it instead of
+/// the project on disk.
+async fn upload_project(State(state): State<Arc<AppState>>, body: Bytes) -> Response {
+    let Some(format) = upload::detect(&body) else {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Json(serde_json::json!({ "error": "Expected a tar.gz or zip archive" })),
+        )
+            .into_response();
+    };
+
+    let limits = state.config.upload_limits;
+    let unpack = move || upload::extract(&body, format, limits);
+    let dir = match tokio::task::spawn_blocking(unpack).await {
+        Ok(Ok(dir)) => dir,
+        Ok(Err(e @ upload::ExtractError::TooLarge(_))) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response();
+        }
+        Ok(Err(e)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": format!("Cannot extract archive: {}", e) })),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response();
+        }
+    };
+
+    let (status, body) = job_response(&state, indexing::try_run_upload(&state, dir).await);
+    (status, Json(body)).into_response()
+}
+
+/// Parse failures from the last successful index run; `null` before one.
+async fn index_errors(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(serde_json::to_value(state.jobs.last_errors()).unwrap())
+}
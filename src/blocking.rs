@@ -0,0 +1,104 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Synchronous adi_core work moved off the async runtime.
+//!
+//! axum drops a handler's future when its client disconnects, but a
+//! `spawn_blocking` task keeps running regardless. Work started through
+//! [`run`] gets a [`Cancellation`] to poll between units of work instead.
+
+use adi_core::Adi;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Set once nobody is waiting for the result anymore.
+#[derive(Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Cancels when the awaiting future is dropped, completed or not.
+struct CancelOnDrop(Cancellation);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        (self.0).0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Runs `work` on the blocking pool. Panics inside `work` resume here, as
+/// if it had run inline.
+pub async fn run<T, F>(work: F) -> T
+where
+    F: FnOnce(&Cancellation) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let cancellation = Cancellation::default();
+    let _guard = CancelOnDrop(cancellation.clone());
+
+    match tokio::task::spawn_blocking(move || work(&cancellation)).await {
+        Ok(value) => value,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => panic!("blocking task did not complete: {}", e),
+    }
+}
+
+/// Runs a synchronous adi_core call on the blocking pool.
+pub async fn query<T, F>(adi: &Arc<Adi>, call: F) -> T
+where
+    F: FnOnce(&Adi) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let adi = adi.clone();
+    run(move |_| call(&adi)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn work_runs_uncancelled_while_awaited() {
+        let cancelled = run(|cancel| cancel.is_cancelled()).await;
+        assert!(!cancelled);
+    }
+
+    #[tokio::test]
+    async fn dropping_the_caller_stops_the_work() {
+        let (started, wait_started) = tokio::sync::oneshot::channel();
+        let (stopped, wait_stopped) = mpsc::channel();
+        // Stands in for a handler whose client disconnected mid-scan
+        let request = tokio::spawn(run(move |cancel| {
+            started.send(()).unwrap();
+            let give_up = Instant::now() + Duration::from_secs(10);
+            while !cancel.is_cancelled() && Instant::now() < give_up {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            stopped.send(cancel.is_cancelled()).unwrap();
+        }));
+        wait_started.await.unwrap();
+
+        let aborted = Instant::now();
+        request.abort();
+        let observed =
+            tokio::task::spawn_blocking(move || wait_stopped.recv_timeout(Duration::from_secs(10)))
+                .await
+                .unwrap();
+
+        assert_eq!(observed, Ok(true));
+        assert!(aborted.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "scan failed")]
+    async fn panics_resume_in_the_caller() {
+        run(|_| panic!("scan failed")).await
+    }
+}
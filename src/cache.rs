@@ -0,0 +1,32 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Aggregations derived from the loaded index, dropped on every reindex.
+#[derive(Default)]
+pub struct IndexCache {
+    /// Symbol kind counts keyed by normalized scope (`""` for the whole
+    /// project), only for scopes containing symbols, tagged with the index
+    /// generation they were counted in
+    pub kind_counts: Mutex<HashMap<String, (u64, BTreeMap<String, usize>)>>,
+    /// File paths by file id, built on first lookup by id, tagged with the
+    /// index generation they were read from
+    pub file_paths: Mutex<Option<(u64, Arc<HashMap<i64, PathBuf>>)>>,
+}
+
+impl IndexCache {
+    pub fn clear(&self) {
+        self.kind_counts.lock().unwrap().clear();
+        *self.file_paths.lock().unwrap() = None;
+    }
+
+    /// Number of cached entries across all caches
+    pub fn len(&self) -> usize {
+        self.kind_counts.lock().unwrap().len()
+            + usize::from(self.file_paths.lock().unwrap().is_some())
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Whole-index enumeration for queries adi_core doesn't answer directly.
+
+use adi_core::{Adi, FileNode, Symbol, SymbolNode, Tree};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Language name as it appears in responses (e.g. `"rust"`).
+pub fn language_name(language: &adi_core::Language) -> String {
+    serde_name(language)
+}
+
+/// Symbol kind as it appears in responses (e.g. `"function"`).
+pub fn kind_name(kind: &adi_core::SymbolKind) -> String {
+    serde_name(kind)
+}
+
+/// Canonical kind a raw kind name reports as, so filters work the same
+/// across languages: `fn`, `def` and `function` are all `function`.
+/// `None` for names that aren't symbol kinds.
+pub fn canonical_kind(kind: &str) -> Option<&'static str> {
+    let canonical = match kind.to_ascii_lowercase().as_str() {
+        "function" | "fn" | "func" | "def" => "function",
+        "method" | "constructor" => "method",
+        "class" => "class",
+        "struct" | "record" => "struct",
+        "interface" | "trait" | "protocol" => "interface",
+        "enum" => "enum",
+        "constant" | "const" | "static" | "enumvariant" => "constant",
+        "variable" | "var" | "let" | "field" | "property" => "variable",
+        "module" | "mod" | "namespace" => "module",
+        "type" | "unknown" | "other" => "other",
+        _ => return None,
+    };
+    Some(canonical)
+}
+
+/// Whether a symbol of `kind` passes a `kind=wanted` filter, comparing
+/// canonical kinds.
+pub fn kind_matches(wanted: &str, kind: &adi_core::SymbolKind) -> bool {
+    canonical_kind(wanted).is_some_and(|wanted| canonical_kind(&kind_name(kind)) == Some(wanted))
+}
+
+fn serde_name(value: &impl serde::Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+/// Canonical form of a project-relative directory filter (`""` for
+/// everything): no leading, trailing or doubled slashes and no `.` segments,
+/// so each directory has exactly one spelling.
+pub fn normalize_scope(scope: &str) -> String {
+    scope
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether a project-relative `path` lies beneath a normalized `scope`.
+pub fn in_scope(path: &Path, scope: &str) -> bool {
+    scope.is_empty() || path.starts_with(scope)
+}
+
+/// Whether symbols in `language` carry parameter and return types.
+pub fn has_type_info(language: &str) -> bool {
+    matches!(
+        language,
+        "rust" | "typescript" | "go" | "java" | "c" | "cpp" | "csharp"
+    )
+}
+
+/// Whether symbols of `kind` (a response name like `"method"`) take
+/// parameters, and so carry a signature.
+pub fn is_callable(kind: &str) -> bool {
+    matches!(canonical_kind(kind), Some("function" | "method"))
+}
+
+/// Full symbol records of every file in `tree`, loaded one file at a time.
+pub fn symbols<'a>(adi: &'a Adi, tree: &'a Tree) -> impl Iterator<Item = Symbol> + 'a {
+    tree.files
+        .iter()
+        .filter_map(move |file| match adi.get_file(&file.path) {
+            Ok(info) => Some(info.symbols),
+            Err(e) => {
+                tracing::debug!("Skipping {}: {}", file.path.display(), e);
+                None
+            }
+        })
+        .flatten()
+}
+
+/// Path of every indexed file by file id; reads each file's record.
+pub fn file_paths(adi: &Adi) -> adi_core::Result<HashMap<i64, PathBuf>> {
+    let tree = adi.get_tree()?;
+    Ok(tree
+        .files
+        .iter()
+        .filter_map(|file| adi.get_file(&file.path).ok())
+        .filter_map(|info| Some((info.file.id?.0, info.file.path)))
+        .collect())
+}
+
+/// Every symbol in `tree`, nested ones included, with the file declaring it.
+pub fn symbol_nodes(tree: &Tree) -> Vec<(&FileNode, &SymbolNode)> {
+    fn walk<'a>(
+        file: &'a FileNode,
+        symbols: &'a [SymbolNode],
+        out: &mut Vec<(&'a FileNode, &'a SymbolNode)>,
+    ) {
+        for symbol in symbols {
+            out.push((file, symbol));
+            walk(file, &symbol.children, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    for file in &tree.files {
+        walk(file, &file.symbols, &mut out);
+    }
+    out
+}
+
+/// Whitespace-insensitive containment, so `Result<Vec<u8>>` matches
+/// `Result< Vec<u8> >`.
+pub fn signature_matches(signature: &str, pattern: &str) -> bool {
+    let strip = |s: &str| s.split_whitespace().collect::<String>();
+    strip(signature).contains(&strip(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_scope_gives_each_directory_one_spelling() {
+        for spelling in ["src/api", "./src/api", "/src/api/", "src//api", "src/./api"] {
+            assert_eq!(normalize_scope(spelling), "src/api", "{}", spelling);
+        }
+    }
+
+    #[test]
+    fn normalize_scope_of_the_root_is_empty() {
+        for spelling in ["", "/", ".", "./", "//"] {
+            assert_eq!(normalize_scope(spelling), "", "{}", spelling);
+        }
+    }
+}
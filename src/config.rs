@@ -0,0 +1,183 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::ranking::{self, Weights};
+use crate::upload;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Server settings read from the environment at startup.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub port: u16,
+    /// Threads driving `adi.index()`; more is faster but holds more files in memory.
+    pub index_threads: usize,
+    /// Build the index at startup when it's missing or unusable
+    pub auto_index: bool,
+    /// Receives a POST after every index run
+    pub webhook_url: Option<String>,
+    /// Bearer token required on `/admin` routes
+    pub admin_token: Option<String>,
+    /// Disables `/admin` routes entirely
+    pub read_only: bool,
+    /// Requests handled at once; more wait for a slot
+    pub max_concurrency: usize,
+    /// Requests allowed to wait for a slot before new ones get `503`
+    pub max_queue: usize,
+    /// Open connections; more wait in the listen backlog
+    pub max_connections: usize,
+    /// Upper bound on a client's `X-Request-Deadline-Ms`
+    pub max_request_deadline: Duration,
+    /// Accept plaintext HTTP/2 with prior knowledge alongside HTTP/1.1
+    pub h2c: bool,
+    /// HTTP/2 ping interval and longest HTTP/1.1 idle wait; `None` also
+    /// closes HTTP/1.1 connections after each response
+    pub keepalive: Option<Duration>,
+    /// Time a client gets to send request headers, including an idle
+    /// HTTP/1.1 connection's next request; `None` waits forever
+    pub header_read_timeout: Option<Duration>,
+    /// `limit` used when a request doesn't give one
+    pub default_limits: DefaultLimits,
+    /// `/search` field weights used when a request doesn't give them
+    pub search_weights: Weights,
+    /// Serves the built-in page at `/ui`
+    pub ui_enabled: bool,
+    /// Largest archive accepted by `/admin/index/upload`
+    pub max_upload_bytes: usize,
+    /// What one uploaded archive may unpack to
+    pub upload_limits: upload::Limits,
+    /// Reindex when files under the project change
+    pub watch: bool,
+    /// Quiet period after the last change before reindexing
+    pub watch_debounce: Duration,
+    /// Age past which a read starts a background reindex; `None` never does
+    pub freshness_ttl: Option<Duration>,
+    /// Wait for the index lock before answering `503`; `None` waits forever
+    pub lock_timeout: Option<Duration>,
+    /// Longest search text accepted, in characters
+    pub max_query_len: usize,
+    /// Send `500` error details to clients instead of only logging them
+    pub debug_errors: bool,
+}
+
+/// Fallback result counts per endpoint.
+#[derive(Debug, Clone)]
+pub struct DefaultLimits {
+    pub search: usize,
+    pub symbols: usize,
+    pub files: usize,
+}
+
+/// Used for any endpoint without its own `DEFAULT_LIMIT_*`.
+const DEFAULT_LIMIT: usize = 10;
+
+impl Config {
+    pub fn from_env() -> Self {
+        let cpus = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        Self {
+            port: env_or("PORT", 3000),
+            index_threads: clamped("INDEX_THREADS", cpus, 1, cpus * 4),
+            auto_index: flag("AUTO_INDEX"),
+            webhook_url: non_empty("WEBHOOK_URL"),
+            admin_token: non_empty("ADMIN_TOKEN"),
+            read_only: flag("READ_ONLY"),
+            max_concurrency: clamped("MAX_CONCURRENCY", 128, 1, 65_536),
+            max_queue: clamped("MAX_QUEUE", 512, 0, 65_536),
+            max_connections: clamped("MAX_CONNECTIONS", 1024, 1, 65_536),
+            h2c: flag_or("H2C", true),
+            keepalive: secs("KEEPALIVE_SECS", 75),
+            header_read_timeout: secs("HEADER_READ_TIMEOUT_SECS", 30),
+            max_request_deadline: Duration::from_millis(clamped(
+                "MAX_REQUEST_DEADLINE_MS",
+                60_000,
+                1,
+                3_600_000,
+            ) as u64),
+            default_limits: DefaultLimits {
+                search: clamped("DEFAULT_LIMIT_SEARCH", DEFAULT_LIMIT, 1, 10_000),
+                symbols: clamped("DEFAULT_LIMIT_SYMBOLS", DEFAULT_LIMIT, 1, 10_000),
+                files: clamped("DEFAULT_LIMIT_FILES", DEFAULT_LIMIT, 1, 10_000),
+            },
+            search_weights: Weights {
+                name: weight("SEARCH_WEIGHT_NAME"),
+                path: weight("SEARCH_WEIGHT_PATH"),
+                doc: weight("SEARCH_WEIGHT_DOC"),
+            },
+            ui_enabled: flag_or("UI_ENABLED", true),
+            max_upload_bytes: clamped("MAX_UPLOAD_BYTES", 100 << 20, 1, usize::MAX),
+            upload_limits: upload::Limits {
+                max_bytes: clamped("MAX_EXTRACTED_BYTES", 1 << 30, 1, usize::MAX) as u64,
+                max_entries: clamped("MAX_ARCHIVE_ENTRIES", 100_000, 1, usize::MAX),
+            },
+            watch: flag("WATCH"),
+            watch_debounce: Duration::from_millis(
+                clamped("WATCH_DEBOUNCE_MS", 500, 10, 60_000) as u64
+            ),
+            freshness_ttl: {
+                let secs = clamped("FRESHNESS_TTL_SECS", 0, 0, 30 * 86_400);
+                (secs > 0).then(|| Duration::from_secs(secs as u64))
+            },
+            lock_timeout: {
+                let ms = clamped("LOCK_TIMEOUT_MS", 5_000, 0, 600_000);
+                (ms > 0).then(|| Duration::from_millis(ms as u64))
+            },
+            max_query_len: clamped("MAX_QUERY_LEN", 1024, 1, 1 << 20),
+            debug_errors: flag("DEBUG_ERRORS"),
+        }
+    }
+}
+
+fn non_empty(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Whole seconds, up to a day; `0` is `None`.
+fn secs(name: &str, default: usize) -> Option<Duration> {
+    let secs = clamped(name, default, 0, 86_400);
+    (secs > 0).then(|| Duration::from_secs(secs as u64))
+}
+
+fn flag(name: &str) -> bool {
+    flag_or(name, false)
+}
+
+fn flag_or(name: &str, default: bool) -> bool {
+    match std::env::var(name).as_deref() {
+        Ok("1" | "true" | "yes" | "on") => true,
+        Ok("0" | "false" | "no" | "off") => false,
+        _ => default,
+    }
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            tracing::warn!("Ignoring invalid {}={:?}", name, value);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+fn clamped(name: &str, default: usize, min: usize, max: usize) -> usize {
+    let value = env_or(name, default);
+    let clamped = value.clamp(min, max);
+    if clamped != value {
+        tracing::warn!("{}={} out of range, using {}", name, value, clamped);
+    }
+    clamped
+}
+
+fn weight(name: &str) -> f32 {
+    let value: f32 = env_or(name, 0.0);
+    if ranking::valid_weight(value) {
+        value
+    } else {
+        tracing::warn!("{}={} out of range, using 0", name, value);
+        0.0
+    }
+}
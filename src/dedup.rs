@@ -0,0 +1,89 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Collapsing `/search` hits that point at the same symbol.
+
+use adi_core::SearchResult;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A `/search` result, plus the other places it was found under `dedup=true`.
+#[derive(Serialize)]
+pub struct Hit {
+    #[serde(flatten)]
+    pub result: SearchResult,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_at: Vec<Occurrence>,
+    /// Matched character ranges in `context` when it isn't marked up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlights: Option<Vec<[usize; 2]>>,
+    /// Score breakdown under `explain=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain: Option<crate::ranking::Explanation>,
+}
+
+#[derive(Serialize)]
+pub struct Occurrence {
+    pub id: Option<adi_core::SymbolId>,
+    pub file_path: PathBuf,
+    pub start_line: u32,
+    pub score: f32,
+}
+
+impl From<SearchResult> for Hit {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            result,
+            also_at: Vec::new(),
+            highlights: None,
+            explain: None,
+        }
+    }
+}
+
+/// Merges results sharing a name, kind and signature (re-exports, aliases)
+/// into the highest-scored one. Order is otherwise preserved.
+pub fn collapse(results: Vec<SearchResult>) -> Vec<Hit> {
+    let mut hits: Vec<Hit> = Vec::with_capacity(results.len());
+    let mut seen: HashMap<(String, String, Option<String>), usize> = HashMap::new();
+
+    for result in results {
+        let symbol = &result.symbol;
+        let key = (
+            symbol.name.clone(),
+            crate::catalog::kind_name(&symbol.kind),
+            symbol.signature.clone(),
+        );
+
+        match seen.get(&key) {
+            Some(&i) => {
+                let mut occurrence = Occurrence::of(&result);
+                let kept = &mut hits[i];
+                if result.score > kept.result.score {
+                    occurrence = Occurrence::of(&kept.result);
+                    kept.result = result;
+                }
+                kept.also_at.push(occurrence);
+            }
+            None => {
+                seen.insert(key, hits.len());
+                hits.push(result.into());
+            }
+        }
+    }
+
+    hits
+}
+
+impl Occurrence {
+    fn of(result: &SearchResult) -> Self {
+        Self {
+            id: result.symbol.id,
+            file_path: result.symbol.file_path.clone(),
+            start_line: result.symbol.location.start_line,
+            score: result.score,
+        }
+    }
+}
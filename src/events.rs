@@ -0,0 +1,72 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::AppState;
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::{Stream, StreamExt};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+/// Slow subscribers that fall this far behind skip ahead to the latest events.
+const CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexUpdated {
+    pub files: u64,
+    pub symbols: u64,
+    pub timestamp_ms: u64,
+}
+
+impl IndexUpdated {
+    pub fn new(files: u64, symbols: u64) -> Self {
+        Self {
+            files,
+            symbols,
+            timestamp_ms: now_ms(),
+        }
+    }
+}
+
+/// Milliseconds since the Unix epoch.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub fn channel() -> broadcast::Sender<IndexUpdated> {
+    broadcast::channel(CHANNEL_CAPACITY).0
+}
+
+/// Tells every `/events` subscriber the index changed.
+pub fn publish(events: &broadcast::Sender<IndexUpdated>, event: IndexUpdated) {
+    // An error only means nobody is subscribed
+    let _ = events.send(event);
+}
+
+pub async fn events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(|event| async move {
+        match event {
+            Ok(event) => Event::default()
+                .event("index_updated")
+                .json_data(event)
+                .ok()
+                .map(Ok),
+            Err(e) => {
+                tracing::debug!("SSE subscriber lagged: {}", e);
+                None
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
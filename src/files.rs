@@ -0,0 +1,651 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::format::ApiJson;
+use crate::{blocking, catalog, indexing, lsp, AppState};
+use axum::{
+    body::Body,
+    extract::{Path as UrlPath, Query, Request, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+/// Matching lines reported per file.
+const MAX_MATCH_LINES: usize = 5;
+
+/// Largest `context_lines` accepted, so snippets can't be used to pull whole files.
+pub const MAX_CONTEXT_LINES: usize = 50;
+
+/// Most paths accepted by one `/files/batch` request.
+pub const MAX_BATCH_PATHS: usize = 100;
+
+/// Largest file whose lines `/files` counts unasked; bigger files only get
+/// a `line_count` when `with_matches` reads them anyway.
+const MAX_LINE_COUNT_BYTES: u64 = 256 * 1024;
+
+/// Files listed by `/files/recent` without a `limit`.
+const DEFAULT_RECENT_LIMIT: usize = 20;
+
+/// A `/files` result: the indexed file plus optional extras.
+#[derive(Serialize)]
+pub struct FileHit {
+    #[serde(flatten)]
+    pub file: adi_core::File,
+    pub size_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches: Option<LineMatches>,
+}
+
+#[derive(Serialize)]
+pub struct LineMatches {
+    pub count: usize,
+    /// First few matching line numbers, 1-based
+    pub lines: Vec<usize>,
+}
+
+impl FileHit {
+    /// Reads the file at most once: for the lines containing `match_query`
+    /// when given, or else for its line count when the index puts it under
+    /// [`MAX_LINE_COUNT_BYTES`]. Lines match ignoring case unless
+    /// `case_sensitive`.
+    pub async fn load(
+        project_path: &Path,
+        file: adi_core::File,
+        match_query: Option<&str>,
+        case_sensitive: bool,
+    ) -> Self {
+        let path = project_path.join(&file.path);
+        let read = match_query.is_some() || file.size <= MAX_LINE_COUNT_BYTES;
+        let content = if read {
+            match tokio::fs::read(&path).await {
+                Ok(content) => Some(content),
+                Err(e) => {
+                    tracing::debug!("Cannot read {}: {}", path.display(), e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let line_count = content.as_deref().map(count_lines);
+        let matches = match (&content, match_query) {
+            (Some(content), Some(query)) => Some(scan_matches(
+                &String::from_utf8_lossy(content),
+                query,
+                case_sensitive,
+            )),
+            _ => None,
+        };
+
+        Self {
+            size_bytes: file.size,
+            modified_at: modified_at(project_path, &file.path).await,
+            file,
+            line_count,
+            matches,
+        }
+    }
+}
+
+/// A file's modification time on disk, RFC 3339 in UTC.
+pub async fn modified_at(project_path: &Path, relative: &Path) -> Option<String> {
+    let metadata = tokio::fs::metadata(project_path.join(relative))
+        .await
+        .ok()?;
+    metadata.modified().ok().map(rfc3339)
+}
+
+/// Whether project-relative `relative` was modified at or after `since`;
+/// `false` when its mtime can't be read.
+pub async fn modified_since(project_path: &Path, relative: &Path, since: SystemTime) -> bool {
+    match tokio::fs::metadata(project_path.join(relative)).await {
+        Ok(metadata) => metadata.modified().is_ok_and(|modified| modified >= since),
+        Err(_) => false,
+    }
+}
+
+/// Parses `modified_after`: an RFC 3339 timestamp with any offset, or a
+/// window back from now such as `7d`, `12h` or `30m`.
+pub fn parse_modified_after(
+    value: &str,
+) -> Result<SystemTime, (StatusCode, Json<serde_json::Value>)> {
+    let value = value.trim();
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.into());
+    }
+    // Also takes a space for `T` and no offset, read as UTC
+    if let Ok(time) = humantime::parse_rfc3339_weak(value) {
+        return Ok(time);
+    }
+    match humantime::parse_duration(value) {
+        Ok(window) => Ok(SystemTime::now()
+            .checked_sub(window)
+            .unwrap_or(SystemTime::UNIX_EPOCH)),
+        Err(_) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "Invalid modified_after {:?}: expected an RFC 3339 timestamp or a duration like 7d",
+                    value
+                )
+            })),
+        )),
+    }
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+#[derive(Deserialize)]
+pub struct RecentQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct RecentFile {
+    path: PathBuf,
+    language: adi_core::Language,
+    modified_at: String,
+}
+
+/// Indexed files by modification time on disk, newest first.
+pub async fn recent(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RecentQuery>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi.as_ref() else {
+        return indexing::not_initialized(&state);
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_RECENT_LIMIT);
+    let project_path = state.project_path();
+    let recent = blocking::query(adi, move |adi| {
+        let tree = adi.get_tree()?;
+        let mut files: Vec<(SystemTime, RecentFile)> = tree
+            .files
+            .into_iter()
+            .filter_map(|file| {
+                let modified = std::fs::metadata(project_path.join(&file.path))
+                    .and_then(|m| m.modified())
+                    .ok()?;
+                let entry = RecentFile {
+                    path: file.path,
+                    language: file.language,
+                    modified_at: rfc3339(modified),
+                };
+                Some((modified, entry))
+            })
+            .collect();
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        adi_core::Result::Ok(
+            files
+                .into_iter()
+                .take(limit)
+                .map(|(_, f)| f)
+                .collect::<Vec<_>>(),
+        )
+    })
+    .await;
+
+    match recent {
+        Ok(files) => (StatusCode::OK, Json(serde_json::to_value(files).unwrap())),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
+}
+
+fn count_lines(content: &[u8]) -> usize {
+    let newlines = content.iter().filter(|&&b| b == b'\n').count();
+    match content.last() {
+        Some(b'\n') | None => newlines,
+        Some(_) => newlines + 1,
+    }
+}
+
+/// Lines containing `query`, ignoring case unless `case_sensitive`.
+fn scan_matches(content: &str, query: &str, case_sensitive: bool) -> LineMatches {
+    let query = if case_sensitive {
+        query.to_string()
+    } else {
+        query.to_lowercase()
+    };
+    let mut matches = LineMatches {
+        count: 0,
+        lines: Vec::new(),
+    };
+
+    for (i, line) in content.lines().enumerate() {
+        let found = if case_sensitive {
+            line.contains(&query)
+        } else {
+            line.to_lowercase().contains(&query)
+        };
+        if found {
+            matches.count += 1;
+            if matches.lines.len() < MAX_MATCH_LINES {
+                matches.lines.push(i + 1);
+            }
+        }
+    }
+
+    matches
+}
+
+#[derive(Deserialize)]
+pub struct FileSymbolsQuery {
+    /// Only symbols of this kind, e.g. `function`
+    pub kind: Option<String>,
+    /// `source` lists symbols in the order they appear in the file
+    pub sort: Option<String>,
+}
+
+/// Whether a `sort` parameter asks for source order; `400` unless it is
+/// `relevance`, `source` or absent.
+pub fn source_order(sort: Option<&str>) -> Result<bool, (StatusCode, Json<serde_json::Value>)> {
+    match sort {
+        None | Some("relevance") => Ok(false),
+        Some("source") => Ok(true),
+        Some(other) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Unknown sort {:?}: expected relevance or source", other)
+            })),
+        )),
+    }
+}
+
+/// Orders `results` by file path, then by where each symbol starts, for
+/// listings in document order. Ties keep their order.
+pub fn sort_by_source<T>(results: &mut [T], symbol: impl Fn(&T) -> &adi_core::Symbol) {
+    results.sort_by(|a, b| {
+        let (a, b) = (symbol(a), symbol(b));
+        let position = |s: &adi_core::Symbol| (s.location.start_line, s.location.start_col);
+        a.file_path
+            .cmp(&b.file_path)
+            .then_with(|| position(a).cmp(&position(b)))
+    });
+}
+
+/// File id from a `/files/<id>/symbols` path. That can't be a route of its
+/// own beside `/files/*path`, so the path route hands it over.
+pub fn symbols_route(path: &str) -> Option<i64> {
+    let (id, rest) = path.split_once('/')?;
+    if rest != "symbols" {
+        return None;
+    }
+    id.parse().ok()
+}
+
+/// Symbols defined in the file with id `id`.
+pub async fn symbols_by_id(
+    state: &AppState,
+    id: i64,
+    query: &FileSymbolsQuery,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let (adi, generation) = match state.read_adi_at().await {
+        Ok(loaded) => loaded,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi else {
+        return indexing::not_initialized(state);
+    };
+
+    // A map read from an index swapped out since is never used
+    let cached = state.cache.file_paths.lock().unwrap().clone();
+    let paths = match cached.filter(|(built, _)| *built == generation) {
+        Some((_, paths)) => paths,
+        None => match blocking::query(&adi, catalog::file_paths).await {
+            Ok(paths) => {
+                let paths = Arc::new(paths);
+                *state.cache.file_paths.lock().unwrap() = Some((generation, paths.clone()));
+                paths
+            }
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": e.to_string() })),
+                );
+            }
+        },
+    };
+
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("File not found: {}", id) })),
+        )
+    };
+    let Some(path) = paths.get(&id).cloned() else {
+        return not_found();
+    };
+    let by_source = match source_order(query.sort.as_deref()) {
+        Ok(by_source) => by_source,
+        Err(rejection) => return rejection,
+    };
+
+    let file = path.clone();
+    match blocking::query(&adi, move |adi| adi.get_file(&file)).await {
+        Ok(info) => {
+            let content = tokio::fs::read(state.project_path().join(&path)).await.ok();
+            let mut symbols: Vec<_> = info
+                .symbols
+                .into_iter()
+                .filter(|s| {
+                    query
+                        .kind
+                        .as_deref()
+                        .is_none_or(|kind| catalog::kind_matches(kind, &s.kind))
+                })
+                .collect();
+            if by_source {
+                sort_by_source(&mut symbols, |s| s);
+            }
+            let symbols: Vec<_> = symbols
+                .into_iter()
+                .map(|symbol| {
+                    let ranges = lsp::Ranges::of(&symbol, content.as_deref());
+                    let mut value = serde_json::to_value(symbol).unwrap();
+                    value["full_range"] = serde_json::to_value(ranges.full_range).unwrap();
+                    value["selection_range"] =
+                        serde_json::to_value(ranges.selection_range).unwrap();
+                    value
+                })
+                .collect();
+            (StatusCode::OK, Json(serde_json::Value::Array(symbols)))
+        }
+        Err(_) => not_found(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BatchRequest {
+    pub paths: Vec<String>,
+}
+
+/// File info for each requested path, `null` where a path is not indexed or
+/// escapes the project. All lookups see the same index.
+pub async fn batch(
+    State(state): State<Arc<AppState>>,
+    ApiJson(request): ApiJson<BatchRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if request.paths.len() > MAX_BATCH_PATHS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("At most {} paths per batch", MAX_BATCH_PATHS)
+            })),
+        );
+    }
+
+    // Every lookup goes to this one index, even if a reindex swaps in another
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi else {
+        return indexing::not_initialized(&state);
+    };
+
+    let project_path = state.project_path();
+    let files = blocking::query(&adi, move |adi| {
+        request
+            .paths
+            .into_iter()
+            .map(|path| {
+                let info = resolve_in_project(&project_path, &path)
+                    .and_then(|_| adi.get_file(Path::new(&path)).ok());
+                (path, info)
+            })
+            .collect::<BTreeMap<_, _>>()
+    })
+    .await;
+
+    (StatusCode::OK, Json(serde_json::to_value(files).unwrap()))
+}
+
+/// Source text spanning a symbol's definition, read from disk.
+pub async fn read_source(project_path: &Path, symbol: &adi_core::Symbol) -> Option<String> {
+    let path = project_path.join(&symbol.file_path);
+    let content = match tokio::fs::read(&path).await {
+        Ok(content) => content,
+        Err(e) => {
+            tracing::debug!("Cannot read source from {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    let location = &symbol.location;
+    let span = content.get(location.start_byte..location.end_byte)?;
+    Some(String::from_utf8_lossy(span).into_owned())
+}
+
+/// `400` unless `context_lines` is within [`MAX_CONTEXT_LINES`].
+pub fn check_context_lines(
+    context_lines: Option<usize>,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    match context_lines {
+        Some(lines) if lines > MAX_CONTEXT_LINES => Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("context_lines must be at most {}", MAX_CONTEXT_LINES)
+            })),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// `lines` lines either side of the line a symbol starts on, read from disk.
+pub async fn read_context(
+    project_path: &Path,
+    symbol: &adi_core::Symbol,
+    lines: usize,
+) -> Option<String> {
+    let path = project_path.join(&symbol.file_path);
+    let content = match tokio::fs::read(&path).await {
+        Ok(content) => content,
+        Err(e) => {
+            tracing::debug!("Cannot read context from {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    // Locations count lines from 1
+    let line = (symbol.location.start_line as usize).saturating_sub(1);
+    let first = line.saturating_sub(lines);
+    let content = String::from_utf8_lossy(&content);
+    let window: Vec<&str> = content
+        .lines()
+        .skip(first)
+        .take(line + lines + 1 - first)
+        .collect();
+    Some(window.join("\n"))
+}
+
+/// Resolves a client-supplied relative path to a file inside the project,
+/// rejecting anything that escapes the root (`..`, absolute paths, symlinks).
+pub fn resolve_in_project(project_path: &Path, relative: &str) -> Option<PathBuf> {
+    let relative = Path::new(relative);
+    if relative.is_absolute() {
+        return None;
+    }
+
+    // Both sides canonical, or a root reached through a symlink (say
+    // `/tmp` on macOS) would reject every file beneath it
+    let root = project_path.canonicalize().ok()?;
+    let resolved = root.join(relative).canonicalize().ok()?;
+    resolved.starts_with(&root).then_some(resolved)
+}
+
+/// Raw bytes of an indexed file, honoring `Range` requests.
+pub async fn raw(
+    State(state): State<Arc<AppState>>,
+    UrlPath(path): UrlPath<String>,
+    request: Request,
+) -> Response {
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("File not found: {}", path) })),
+        )
+            .into_response()
+    };
+
+    let Some(full_path) = resolve_in_project(&state.project_path(), &path) else {
+        return not_found();
+    };
+
+    // Only serve what the index knows about, never arbitrary project files
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let indexed = PathBuf::from(&path);
+    match adi.as_ref() {
+        Some(adi) if blocking::query(adi, move |adi| adi.get_file(&indexed).is_ok()).await => {}
+        Some(_) => return not_found(),
+        None => return indexing::not_initialized(&state).into_response(),
+    }
+
+    match ServeFile::new(full_path).oneshot(request).await {
+        Ok(response) => response.map(Body::new),
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn scan_matches_ignores_case_by_default() {
+        let matches = scan_matches("Config\nconfig\nother\nCONFIG", "config", false);
+        assert_eq!(matches.count, 3);
+        assert_eq!(matches.lines, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn scan_matches_compares_case_when_asked() {
+        let matches = scan_matches("Config\nconfig\nother\nCONFIG", "Config", true);
+        assert_eq!(matches.count, 1);
+        assert_eq!(matches.lines, vec![1]);
+    }
+
+    #[test]
+    fn scan_matches_counts_past_the_reported_lines() {
+        let content = "x\n".repeat(MAX_MATCH_LINES + 2);
+        let matches = scan_matches(&content, "x", true);
+        assert_eq!(matches.count, MAX_MATCH_LINES + 2);
+        assert_eq!(matches.lines.len(), MAX_MATCH_LINES);
+    }
+
+    #[test]
+    fn parse_modified_after_takes_utc_timestamps() {
+        let parsed = parse_modified_after("2024-05-01T00:00:00Z").unwrap();
+        assert_eq!(parsed, at(1_714_521_600));
+    }
+
+    #[test]
+    fn parse_modified_after_takes_numeric_offsets() {
+        let parsed = parse_modified_after("2024-05-01T02:00:00+02:00").unwrap();
+        assert_eq!(parsed, at(1_714_521_600));
+        let parsed = parse_modified_after("2024-04-30T19:30:00-04:30").unwrap();
+        assert_eq!(parsed, at(1_714_521_600));
+    }
+
+    #[test]
+    fn parse_modified_after_takes_windows_back_from_now() {
+        let parsed = parse_modified_after("7d").unwrap();
+        let expected = SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+        let drift = expected
+            .duration_since(parsed)
+            .unwrap_or_else(|e| e.duration());
+        assert!(drift < Duration::from_secs(5), "{:?}", drift);
+    }
+
+    #[test]
+    fn parse_modified_after_rejects_anything_else() {
+        let (status, _) = parse_modified_after("last tuesday").unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    /// `project/lib/lib.rs` plus `outside/secret`, next to each other.
+    fn project_with_outside() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let (project, outside) = (root.join("project"), root.join("outside"));
+        std::fs::create_dir_all(project.join("lib")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(project.join("lib/lib.rs"), "").unwrap();
+        std::fs::write(outside.join("secret"), "").unwrap();
+        (dir, project, outside)
+    }
+
+    #[test]
+    fn resolve_in_project_rejects_escapes() {
+        let (_dir, project, _) = project_with_outside();
+        assert_eq!(resolve_in_project(&project, "../outside/secret"), None);
+        let absolute = project.join("lib/lib.rs");
+        assert_eq!(
+            resolve_in_project(&project, &absolute.to_string_lossy()),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_in_project_follows_a_symlinked_root() {
+        let (dir, project, _) = project_with_outside();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&project, &link).unwrap();
+
+        let resolved = resolve_in_project(&link, "lib/lib.rs");
+
+        assert_eq!(resolved, Some(project.join("lib/lib.rs")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_in_project_follows_symlinked_subdirs_inside_the_root() {
+        let (_dir, project, _) = project_with_outside();
+        std::os::unix::fs::symlink(project.join("lib"), project.join("src")).unwrap();
+
+        let resolved = resolve_in_project(&project, "src/lib.rs");
+
+        assert_eq!(resolved, Some(project.join("lib/lib.rs")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_in_project_rejects_symlinked_subdirs_leaving_the_root() {
+        let (_dir, project, outside) = project_with_outside();
+        std::os::unix::fs::symlink(&outside, project.join("escape")).unwrap();
+
+        assert_eq!(resolve_in_project(&project, "escape/secret"), None);
+    }
+}
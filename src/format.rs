@@ -0,0 +1,579 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Response post-processing: output formats and uniform error bodies.
+
+use crate::{catalog, AppState};
+use axum::{
+    async_trait,
+    body::{to_bytes, Body, Bytes},
+    extract::{FromRequest, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{Map, Value};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// Columns of CSV exports, in order.
+const CSV_COLUMNS: [&str; 5] = ["path", "name", "kind", "line", "score"];
+
+/// Whether query string `query` sets boolean flag `name`.
+pub fn query_flag(query: Option<&str>, name: &str) -> bool {
+    query.unwrap_or_default().split('&').any(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, "true"));
+        key == name && matches!(value, "true" | "1")
+    })
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// Re-serializes JSON responses with indentation for `?pretty=true`.
+pub async fn pretty(request: Request, next: Next) -> Response {
+    let wanted = query_flag(request.uri().query(), "pretty");
+    let response = next.run(request).await;
+
+    if !wanted || !is_json(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for pretty-printing: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(value) => serde_json::to_vec_pretty(&value).unwrap_or_else(|_| bytes.to_vec()),
+        Err(_) => bytes.to_vec(),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Reports every symbol's `kind` in a symbol route's response under its
+/// canonical name; see [`canonicalize_kinds`].
+pub async fn canonical_kinds(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if !is_json(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer response for kind names: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to read response body" })),
+            )
+                .into_response();
+        }
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    canonicalize_kinds(&mut value);
+    let Ok(body) = serde_json::to_vec(&value) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Renames every `kind` in `value` to its canonical name (see
+/// [`catalog::canonical_kind`]), keeping adi_core's name as `raw_kind`.
+pub fn canonicalize_kinds(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            let canonical = match object.get("kind") {
+                Some(Value::String(raw)) if !object.contains_key("raw_kind") => {
+                    catalog::canonical_kind(raw)
+                }
+                _ => None,
+            };
+            if let Some(canonical) = canonical {
+                let raw = object.insert("kind".into(), canonical.into());
+                object.insert("raw_kind".into(), raw.unwrap_or_default());
+            }
+            object.values_mut().for_each(canonicalize_kinds);
+        }
+        Value::Array(items) => items.iter_mut().for_each(canonicalize_kinds),
+        _ => {}
+    }
+}
+
+/// Answers `Accept: text/csv` with one row per result instead of JSON.
+///
+/// The JSON body is buffered and parsed before rows are written. Handlers
+/// hold every result in memory anyway, as adi_core returns them in one
+/// `Vec`, so this costs one more copy of the page rather than of the index.
+pub async fn csv(request: Request, next: Next) -> Response {
+    let wanted = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|t| t.trim().starts_with("text/csv")));
+    let response = next.run(request).await;
+
+    if !wanted || !response.status().is_success() || !is_json(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let results = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => match serde_json::from_slice::<Value>(&bytes) {
+            // Responses carrying metadata wrap the list in `results`
+            Ok(Value::Object(mut body)) => body.remove("results").unwrap_or_default(),
+            Ok(value) => value,
+            Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+        },
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for CSV export: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Value::Array(results) = results else {
+        return Response::from_parts(parts, Body::empty());
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/csv; charset=utf-8"),
+    );
+
+    let header_row = format!("{}\n", CSV_COLUMNS.join(","));
+    let rows = std::iter::once(header_row).chain(results.into_iter().map(|r| csv_row(&r)));
+    let stream = futures_util::stream::iter(rows.map(Ok::<_, Infallible>));
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+/// A search hit, symbol or file flattened into [`CSV_COLUMNS`].
+fn csv_row(result: &Value) -> String {
+    // `/search` nests the symbol; `/symbols` and `/files` don't
+    let item = result.get("symbol").unwrap_or(result);
+    let path = item.get("file_path").or_else(|| item.get("path"));
+    let line = item.pointer("/location/start_line");
+    // Exported before `canonical_kinds` sees the response
+    let kind = item.get("kind").map(|kind| {
+        let canonical = kind.as_str().and_then(catalog::canonical_kind);
+        canonical.map_or_else(|| kind.clone(), Value::from)
+    });
+
+    let fields = [
+        path,
+        item.get("name"),
+        kind.as_ref(),
+        line,
+        result.get("score"),
+    ];
+    let mut row = fields
+        .iter()
+        .map(|field| csv_field(field.unwrap_or(&Value::Null)))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push('\n');
+    row
+}
+
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+#[derive(Deserialize)]
+struct FieldsQuery {
+    fields: Option<String>,
+}
+
+/// Trims each result to the comma-separated `fields`, e.g.
+/// `?fields=name,path`. Search hits are flattened so symbol fields sit
+/// beside `score`; `path` also finds a symbol's `file_path`. Unknown names
+/// are skipped.
+pub async fn fields(request: Request, next: Next) -> Response {
+    let fields = Query::<FieldsQuery>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(query)| query.fields);
+    let response = next.run(request).await;
+
+    let Some(fields) = fields else {
+        return response;
+    };
+    if !response.status().is_success() || !is_json(response.headers()) {
+        return response;
+    }
+    let fields: Vec<&str> = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .collect();
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for field projection: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let projected = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Array(results)) => project_all(results, &fields),
+        // Responses carrying metadata wrap the list in `results`
+        Ok(Value::Object(mut body)) if body.get("results").is_some_and(Value::is_array) => {
+            if let Some(Value::Array(results)) = body.remove("results") {
+                body.insert("results".into(), project_all(results, &fields));
+            }
+            Value::Object(body)
+        }
+        _ => return Response::from_parts(parts, Body::from(bytes)),
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(projected.to_string()))
+}
+
+fn project_all(results: Vec<Value>, fields: &[&str]) -> Value {
+    results.iter().map(|r| project(r, fields)).collect()
+}
+
+fn project(result: &Value, fields: &[&str]) -> Value {
+    // `group_by=file` groups keep their shape; their matches are projected
+    if let (Some(file), Some(Value::Array(matches))) = (result.get("file"), result.get("matches")) {
+        return serde_json::json!({
+            "file": file,
+            "count": result.get("count"),
+            "matches": matches.iter().map(|m| project(m, fields)).collect::<Vec<_>>(),
+        });
+    }
+
+    let symbol = result.get("symbol");
+    let mut projected = Map::new();
+    for &field in fields {
+        let value = lookup(result, field).or_else(|| symbol.and_then(|s| lookup(s, field)));
+        if let Some(value) = value {
+            projected.insert(field.to_string(), value.clone());
+        }
+    }
+    Value::Object(projected)
+}
+
+fn lookup<'a>(item: &'a Value, field: &str) -> Option<&'a Value> {
+    item.get(field).or_else(|| match field {
+        "path" => item.get("file_path"),
+        _ => None,
+    })
+}
+
+#[derive(Deserialize)]
+struct GroupQuery {
+    group_by: Option<String>,
+}
+
+/// Nests results under their file for `?group_by=file`, as
+/// `[{file, count, matches}]`. Results arrive best first, so files are
+/// ordered by their best match.
+pub async fn group_by_file(request: Request, next: Next) -> Response {
+    let group_by = Query::<GroupQuery>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(query)| query.group_by);
+    match group_by.as_deref() {
+        None => return next.run(request).await,
+        Some("file") => {}
+        Some(other) => {
+            let body = serde_json::json!({ "error": format!("Unknown group_by: {}", other) });
+            return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+        }
+    }
+
+    let response = next.run(request).await;
+    if !response.status().is_success() || !is_json(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for grouping: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let grouped = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Array(results)) => group(results),
+        // Responses carrying metadata wrap the list in `results`
+        Ok(Value::Object(mut body)) if body.get("results").is_some_and(Value::is_array) => {
+            if let Some(Value::Array(results)) = body.remove("results") {
+                body.insert("results".into(), group(results));
+            }
+            Value::Object(body)
+        }
+        _ => return Response::from_parts(parts, Body::from(bytes)),
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(grouped.to_string()))
+}
+
+fn group(results: Vec<Value>) -> Value {
+    let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+    for result in results {
+        // `/search` nests the symbol; `/symbols` doesn't
+        let item = result.get("symbol").unwrap_or(&result);
+        let file = item.get("file_path").cloned().unwrap_or_default();
+        match groups.iter_mut().find(|(f, _)| *f == file) {
+            Some((_, matches)) => matches.push(result),
+            None => groups.push((file, vec![result])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(file, matches)| {
+            serde_json::json!({ "file": file, "count": matches.len(), "matches": matches })
+        })
+        .collect()
+}
+
+/// Hides the detail of `500` responses unless `DEBUG_ERRORS` is set: the
+/// error is logged with the request's id, and the client gets a generic
+/// message naming that id as `correlation_id`.
+pub async fn internal_errors(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let correlation_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let response = next.run(request).await;
+    if response.status() != StatusCode::INTERNAL_SERVER_ERROR || state.config.debug_errors {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let detail = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => serde_json::from_slice::<Value>(&bytes)
+            .ok()
+            .and_then(|body| body.get("error").and_then(Value::as_str).map(str::to_owned))
+            .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned()),
+        Err(e) => e.to_string(),
+    };
+    tracing::error!(correlation_id = %correlation_id, "Internal error: {}", detail);
+
+    let body = serde_json::json!({
+        "error": "Internal server error",
+        "correlation_id": correlation_id,
+    });
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(body.to_string()))
+}
+
+/// Gives axum's bare `405` a JSON body naming the allowed methods. The
+/// `Allow` header it already sets is kept.
+pub async fn method_not_allowed(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED || is_json(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, _) = response.into_parts();
+    let allow: Vec<&str> = parts
+        .headers
+        .get(header::ALLOW)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|m| !m.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let body = serde_json::json!({ "error": "Method not allowed", "allow": allow });
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(body.to_string()))
+}
+
+/// A JSON request body whose rejections use the API's error body instead of
+/// axum's plain text. An unparseable or mistyped body is `400` with
+/// `code: "bad_json"` and the `line` and `column` serde stopped at.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<Value>);
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json(request.headers()) {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                Json(serde_json::json!({
+                    "error": "Expected Content-Type: application/json",
+                    "code": "unsupported_media_type",
+                })),
+            ));
+        }
+
+        let bytes = Bytes::from_request(request, state)
+            .await
+            .map_err(|rejection| {
+                let code = match rejection.status() {
+                    StatusCode::PAYLOAD_TOO_LARGE => "too_large",
+                    _ => "bad_body",
+                };
+                (
+                    rejection.status(),
+                    Json(serde_json::json!({ "error": rejection.body_text(), "code": code })),
+                )
+            })?;
+
+        serde_json::from_slice(&bytes).map(Self).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("Invalid JSON body: {}", e),
+                    "code": "bad_json",
+                    "line": e.line(),
+                    "column": e.column(),
+                })),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::post, Router};
+    use tower::ServiceExt;
+
+    async fn send(app: Router, request: Request) -> (StatusCode, HeaderMap, Value) {
+        let response = app.oneshot(request).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let bytes = to_bytes(body, usize::MAX).await.unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (parts.status, parts.headers, body)
+    }
+
+    /// Methods named by a comma-separated header value or JSON list, sorted.
+    fn methods<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+        let mut names: Vec<String> = names.map(|m| m.trim().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn method_not_allowed_lists_the_allowed_methods() {
+        let routes = Router::new().route("/index", post(|| async {}).delete(|| async {}));
+        // Wrapped around the whole router, as in `main`
+        let app = Router::new()
+            .fallback_service(routes)
+            .layer(middleware::from_fn(method_not_allowed));
+        let request = axum::http::Request::get("/index")
+            .body(Body::empty())
+            .unwrap();
+
+        let (status, headers, body) = send(app, request).await;
+
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        let allow = headers.get(header::ALLOW).unwrap().to_str().unwrap();
+        assert_eq!(methods(allow.split(',')), ["DELETE", "POST"]);
+        assert_eq!(body["error"], "Method not allowed");
+        let listed = body["allow"].as_array().unwrap();
+        assert_eq!(
+            methods(listed.iter().filter_map(Value::as_str)),
+            ["DELETE", "POST"]
+        );
+    }
+
+    #[derive(Deserialize)]
+    struct Batch {
+        #[allow(dead_code)]
+        queries: Vec<String>,
+    }
+
+    fn batch_app() -> Router {
+        Router::new().route("/batch", post(|ApiJson(_): ApiJson<Batch>| async {}))
+    }
+
+    fn post_json(content_type: &str, body: &'static str) -> Request {
+        axum::http::Request::post("/batch")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_bad_json_with_its_location() {
+        let request = post_json("application/json", "{\n  \"queries\": [\"a\",]\n}");
+
+        let (status, _, body) = send(batch_app(), request).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "bad_json");
+        assert_eq!(body["line"], 2);
+        assert!(body["column"].as_u64().is_some_and(|column| column > 0));
+        let error = body["error"].as_str().unwrap();
+        assert!(error.starts_with("Invalid JSON body"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn mistyped_json_is_bad_json() {
+        let request = post_json("application/json", r#"{"queries": "a"}"#);
+
+        let (status, _, body) = send(batch_app(), request).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "bad_json");
+    }
+
+    #[tokio::test]
+    async fn other_content_types_are_unsupported() {
+        let request = post_json("text/plain", r#"{"queries": []}"#);
+
+        let (status, _, body) = send(batch_app(), request).await;
+
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(body["code"], "unsupported_media_type");
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Lazy reindexing under `FRESHNESS_TTL_SECS`: a read that finds the index
+//! older than the TTL starts a background run and is still answered from
+//! the old index, marked `X-Index-Stale: true`.
+
+use crate::{events, indexing, schema, AppState};
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Paths that don't read the index, so never count as a use of it.
+const NON_READS: [&str; 7] = [
+    "/",
+    "/health",
+    "/readyz",
+    "/version",
+    "/capabilities",
+    "/events",
+    "/ui",
+];
+
+#[derive(Default)]
+pub struct Freshness {
+    /// Unix time the served index was built, zero when none is loaded
+    built_at_ms: AtomicU64,
+    /// A lazy reindex has been started and not yet finished
+    refreshing: AtomicBool,
+}
+
+impl Freshness {
+    /// Dates an index loaded at startup by its schema marker, which every
+    /// run rewrites.
+    pub fn loaded(project_path: &Path) -> Self {
+        let freshness = Self::default();
+        if let Some(built) = schema::recorded_at(project_path) {
+            let ms = built.duration_since(UNIX_EPOCH).unwrap_or_default();
+            freshness
+                .built_at_ms
+                .store(ms.as_millis() as u64, Ordering::Relaxed);
+        }
+        freshness
+    }
+
+    /// Notes that a freshly built index is now served.
+    pub fn built(&self) {
+        self.built_at_ms.store(events::now_ms(), Ordering::Relaxed);
+    }
+
+    fn older_than(&self, ttl: Duration) -> bool {
+        let built = self.built_at_ms.load(Ordering::Relaxed);
+        built > 0 && events::now_ms().saturating_sub(built) > ttl.as_millis() as u64
+    }
+}
+
+/// Starts a background reindex when a read finds the index past its TTL,
+/// and marks responses served from it meanwhile.
+pub async fn check(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let Some(ttl) = state.config.freshness_ttl else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    let read = request.method() == Method::GET
+        && !NON_READS.contains(&path)
+        && !path.starts_with("/admin");
+    if !read || !state.freshness.older_than(ttl) {
+        return next.run(request).await;
+    }
+
+    // One lazy run at a time, and none on top of a run started elsewhere
+    if state.jobs.current().is_none() && !state.freshness.refreshing.swap(true, Ordering::AcqRel) {
+        let state = state.clone();
+        tokio::spawn(async move {
+            tracing::info!("Index older than {}s, reindexing", ttl.as_secs());
+            if let Err(e) = indexing::run(&state).await {
+                tracing::error!("Lazy reindex failed: {}", e);
+            }
+            state.freshness.refreshing.store(false, Ordering::Release);
+        });
+    }
+
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .insert("x-index-stale", HeaderValue::from_static("true"));
+    response
+}
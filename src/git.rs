@@ -0,0 +1,227 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Git metadata about the project, for the features that need it.
+
+use axum::{http::StatusCode, Json};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+/// Whether `project_path` lies inside a git work tree, judged by a `.git`
+/// entry in it or any ancestor (a file for worktrees and submodules).
+pub fn is_repository(project_path: &Path) -> bool {
+    project_path
+        .ancestors()
+        .any(|dir| dir.join(".git").exists())
+}
+
+/// `400` for a git-backed `feature` requested on a plain directory.
+pub fn not_a_repository(feature: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({
+            "error": "project is not a git repository",
+            "feature": feature,
+        })),
+    )
+}
+
+/// Commit checked out in the project, if git can tell.
+pub async fn head_commit(project_path: &Path) -> Option<String> {
+    let output = tokio::process::Command::new("git")
+        .arg("rev-parse")
+        .arg("HEAD")
+        .current_dir(project_path)
+        .output()
+        .await
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Longest a `git blame` may run before it's killed; blame walks history,
+/// which can take long on deep histories or huge files.
+const BLAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Last change to one line, as `git blame` attributes it.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlameLine {
+    /// 1-based, in the file as checked out
+    pub line: u32,
+    /// All zeros for changes not committed yet
+    pub commit: String,
+    pub author: String,
+    pub author_email: String,
+    /// Unix seconds
+    pub author_time: i64,
+    pub summary: String,
+}
+
+/// `git blame` of lines `start..=end` (1-based) of project-relative `file`.
+/// Fails with `404` and git's own complaint when git can't blame the file,
+/// e.g. when it isn't tracked, and `503` when blame runs too long.
+pub async fn blame(
+    project_path: &Path,
+    file: &Path,
+    start: u32,
+    end: u32,
+) -> Result<Vec<BlameLine>, (StatusCode, String)> {
+    let mut command = tokio::process::Command::new("git");
+    command
+        .arg("blame")
+        .arg("--line-porcelain")
+        .arg("-L")
+        .arg(format!("{},{}", start, end))
+        .arg("--")
+        .arg(file)
+        .current_dir(project_path)
+        // Reaped if the timeout below drops the wait
+        .kill_on_drop(true);
+    let output = match tokio::time::timeout(BLAME_TIMEOUT, command.output()).await {
+        Ok(output) => output.map_err(|e| {
+            let error = format!("cannot run git: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, error)
+        })?,
+        Err(_) => {
+            let error = format!("git blame took longer than {}s", BLAME_TIMEOUT.as_secs());
+            return Err((StatusCode::SERVICE_UNAVAILABLE, error));
+        }
+    };
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err((StatusCode::NOT_FOUND, error));
+    }
+    Ok(parse_line_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// `--line-porcelain` repeats the full commit header before every line, so
+/// each entry stands alone: `<sha> <orig> <final> [<count>]`, `key value`
+/// fields, then the line's content after a tab.
+fn parse_line_porcelain(output: &str) -> Vec<BlameLine> {
+    let mut lines = Vec::new();
+    let mut current: Option<BlameLine> = None;
+
+    for row in output.lines() {
+        if row.starts_with('\t') {
+            lines.extend(current.take());
+            continue;
+        }
+        let Some(entry) = current.as_mut() else {
+            let mut header = row.split(' ');
+            let commit = header.next().unwrap_or_default().to_string();
+            let line = header
+                .nth(1)
+                .and_then(|n| n.parse().ok())
+                .unwrap_or_default();
+            current = Some(BlameLine {
+                line,
+                commit,
+                author: String::new(),
+                author_email: String::new(),
+                author_time: 0,
+                summary: String::new(),
+            });
+            continue;
+        };
+        let (key, value) = row.split_once(' ').unwrap_or((row, ""));
+        match key {
+            "author" => entry.author = value.to_string(),
+            "author-mail" => {
+                entry.author_email = value.trim_matches(|c| c == '<' || c == '>').to_string()
+            }
+            "author-time" => entry.author_time = value.parse().unwrap_or_default(),
+            "summary" => entry.summary = value.to_string(),
+            _ => {}
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORCELAIN: &str = "\
+4b825dc642cb6eb9a060e54bf8d69288fbee4904 3 10 2
+author Ada Lovelace
+author-mail <ada@example.com>
+author-time 1700000000
+author-tz +0000
+committer Ada Lovelace
+committer-mail <ada@example.com>
+committer-time 1700000000
+committer-tz +0000
+summary Add the loader
+previous 1111111111111111111111111111111111111111 src/lib.rs
+filename src/lib.rs
+\tfn load() {
+4b825dc642cb6eb9a060e54bf8d69288fbee4904 4 11
+author Ada Lovelace
+author-mail <ada@example.com>
+author-time 1700000000
+author-tz +0000
+committer Ada Lovelace
+committer-mail <ada@example.com>
+committer-time 1700000000
+committer-tz +0000
+summary Add the loader
+filename src/lib.rs
+\t    read()
+0000000000000000000000000000000000000000 12 12 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+author-time 1710000000
+author-tz +0000
+committer Not Committed Yet
+committer-mail <not.committed.yet>
+committer-time 1710000000
+committer-tz +0000
+summary Version of src/lib.rs from src/lib.rs
+filename src/lib.rs
+\t}
+";
+
+    #[test]
+    fn parse_line_porcelain_reads_one_entry_per_line() {
+        let lines = parse_line_porcelain(PORCELAIN);
+
+        let numbers: Vec<u32> = lines.iter().map(|l| l.line).collect();
+        assert_eq!(numbers, [10, 11, 12]);
+        let first = &lines[0];
+        assert_eq!(first.commit, "4b825dc642cb6eb9a060e54bf8d69288fbee4904");
+        assert_eq!(first.author, "Ada Lovelace");
+        assert_eq!(first.author_email, "ada@example.com");
+        assert_eq!(first.author_time, 1_700_000_000);
+        assert_eq!(first.summary, "Add the loader");
+    }
+
+    #[test]
+    fn parse_line_porcelain_keeps_uncommitted_lines() {
+        let lines = parse_line_porcelain(PORCELAIN);
+
+        let last = lines.last().unwrap();
+        assert!(last.commit.chars().all(|c| c == '0'));
+        assert_eq!(last.author, "Not Committed Yet");
+    }
+
+    #[test]
+    fn parse_line_porcelain_of_nothing_is_empty() {
+        assert!(parse_line_porcelain("").is_empty());
+    }
+
+    #[test]
+    fn parse_line_porcelain_reads_tabs_in_content_as_content() {
+        let output = "abc 1 1 1\nauthor A\n\t\tindented\n";
+        let lines = parse_line_porcelain(output);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].author, "A");
+    }
+}
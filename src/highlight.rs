@@ -0,0 +1,75 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Marking query terms in `/search` snippets.
+
+/// Byte ranges of `text` matching any whitespace-separated term of `query`,
+/// case-insensitively, sorted and merged.
+pub fn spans(text: &str, query: &str) -> Vec<(usize, usize)> {
+    // ASCII folding keeps byte offsets valid in the original text
+    let haystack = text.to_ascii_lowercase();
+    let mut spans: Vec<(usize, usize)> = query
+        .split_whitespace()
+        .map(str::to_ascii_lowercase)
+        .flat_map(|term| {
+            haystack
+                .match_indices(&term)
+                .map(|(start, m)| (start, start + m.len()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// `spans` as `[start, end)` character offsets, for clients that don't
+/// index strings by byte.
+pub fn char_offsets(text: &str, spans: &[(usize, usize)]) -> Vec<[usize; 2]> {
+    let chars = |byte: usize| text[..byte].chars().count();
+    spans
+        .iter()
+        .map(|&(start, end)| [chars(start), chars(end)])
+        .collect()
+}
+
+/// `text` with every span wrapped in `pre`/`post`. The text itself is
+/// HTML-escaped, the markers are not.
+pub fn wrap(text: &str, spans: &[(usize, usize)], pre: &str, post: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0;
+
+    for &(start, end) in spans {
+        out.push_str(&escape_html(&text[cursor..start]));
+        out.push_str(pre);
+        out.push_str(&escape_html(&text[start..end]));
+        out.push_str(post);
+        cursor = end;
+    }
+    out.push_str(&escape_html(&text[cursor..]));
+
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! The previous index's symbol table, kept to diff symbols across a reindex.
+
+use crate::catalog;
+use adi_core::{Adi, Symbol};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What a symbol looked like in the previous index.
+#[derive(Debug, Clone, Serialize)]
+pub struct Definition {
+    pub file_path: PathBuf,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub signature: Option<String>,
+    #[serde(skip)]
+    len_bytes: usize,
+}
+
+impl Definition {
+    fn of(symbol: &Symbol) -> Self {
+        let location = &symbol.location;
+        Self {
+            file_path: symbol.file_path.clone(),
+            start_line: location.start_line,
+            end_line: location.end_line,
+            signature: symbol.signature.clone(),
+            len_bytes: location.end_byte.saturating_sub(location.start_byte),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    New,
+    Moved,
+    Modified,
+    Unchanged,
+}
+
+#[derive(Serialize)]
+pub struct SymbolDiff {
+    pub status: Change,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Definition>,
+}
+
+/// Definitions keyed by name and kind, since ids aren't stable across indexes.
+#[derive(Default)]
+pub struct Snapshot {
+    definitions: HashMap<(String, String), Vec<Definition>>,
+}
+
+impl Snapshot {
+    /// Reads every symbol of `adi`; blocking, so run it off the request path.
+    pub fn of(adi: &Adi) -> adi_core::Result<Self> {
+        let tree = adi.get_tree()?;
+        let mut snapshot = Self::default();
+
+        for symbol in catalog::symbols(adi, &tree) {
+            snapshot
+                .definitions
+                .entry(key(&symbol))
+                .or_default()
+                .push(Definition::of(&symbol));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// How `symbol` differs from its closest match in this snapshot.
+    pub fn diff(&self, symbol: &Symbol) -> SymbolDiff {
+        let candidates = self.definitions.get(&key(symbol)).map(Vec::as_slice);
+        let same_file = candidates
+            .unwrap_or_default()
+            .iter()
+            .filter(|d| d.file_path == symbol.file_path)
+            .min_by_key(|d| d.start_line.abs_diff(symbol.location.start_line));
+
+        let Some(previous) = same_file.or_else(|| candidates.and_then(|c| c.first())) else {
+            return SymbolDiff {
+                status: Change::New,
+                previous: None,
+            };
+        };
+
+        let current = Definition::of(symbol);
+        let status =
+            if current.signature != previous.signature || current.len_bytes != previous.len_bytes {
+                Change::Modified
+            } else if current.file_path != previous.file_path
+                || current.start_line != previous.start_line
+            {
+                Change::Moved
+            } else {
+                Change::Unchanged
+            };
+
+        SymbolDiff {
+            status,
+            previous: Some(previous.clone()),
+        }
+    }
+}
+
+fn key(symbol: &Symbol) -> (String, String) {
+    (symbol.name.clone(), catalog::kind_name(&symbol.kind))
+}
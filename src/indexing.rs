@@ -0,0 +1,605 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::events::{self, IndexUpdated};
+use crate::history::Snapshot;
+use crate::stats::INDEX_DIR_NAME;
+use crate::{blocking, schema, AppState};
+use axum::{http::StatusCode, Json};
+use serde::Serialize;
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Runtime};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+/// Reindexes and reports the outcome to the webhook, if configured. Waits
+/// for a run already in progress to finish first.
+pub async fn run(state: &AppState) -> anyhow::Result<adi_core::IndexProgress> {
+    let turn = state.jobs.wait_turn().await;
+    tracked(state, turn, reindex(state)).await.1
+}
+
+/// Like [`run`], also returning the id the job was tracked under, but
+/// refused with the running job while another run is in progress.
+pub async fn try_run_job(
+    state: &AppState,
+) -> Result<(u64, anyhow::Result<adi_core::IndexProgress>), Option<JobReport>> {
+    let turn = state.jobs.try_turn()?;
+    Ok(tracked(state, turn, reindex(state)).await)
+}
+
+/// Indexes an extracted upload and, on success, serves it in place of the
+/// current project. `dir` is deleted on failure, or once it's replaced.
+/// Refused like [`try_run_job`] while another run is in progress.
+pub async fn try_run_upload(
+    state: &AppState,
+    dir: tempfile::TempDir,
+) -> Result<(u64, anyhow::Result<adi_core::IndexProgress>), Option<JobReport>> {
+    let turn = state.jobs.try_turn()?;
+    Ok(tracked(state, turn, index_upload(state, dir)).await)
+}
+
+/// Runs `work` as the job holding `turn` and reports it to the webhook.
+async fn tracked(
+    state: &AppState,
+    turn: Turn<'_>,
+    work: impl Future<Output = anyhow::Result<adi_core::IndexProgress>>,
+) -> (u64, anyhow::Result<adi_core::IndexProgress>) {
+    let started = Instant::now();
+    let job = turn.job;
+    let outcome = work.await;
+    let elapsed = started.elapsed();
+    state.jobs.finish(job, outcome.is_ok());
+    if let Ok(progress) = &outcome {
+        if !progress.errors.is_empty() {
+            tracing::warn!("{} files failed to index", progress.errors.len());
+        }
+        state.jobs.record_errors(job, &progress.errors);
+        let bytes = indexed_bytes(state).await;
+        let throughput = Throughput::new(job, progress.files_processed, bytes, elapsed);
+        tracing::info!(
+            "Indexed {:.1} files/s, {:.0} bytes/s",
+            throughput.files_per_sec,
+            throughput.bytes_per_sec
+        );
+        state.jobs.record_throughput(throughput);
+    }
+
+    if let Some(webhook) = &state.webhook {
+        webhook.index_completed(&state.project_path(), &outcome, started.elapsed());
+    }
+
+    drop(turn);
+    (job, outcome)
+}
+
+/// Total size on disk of the files the served index covers.
+async fn indexed_bytes(state: &AppState) -> u64 {
+    let Some(adi) = state.adi.read().await.clone() else {
+        return 0;
+    };
+    let project_path = state.project_path();
+    blocking::query(&adi, move |adi| {
+        adi.get_tree().map_or(0, |tree| {
+            tree.files
+                .iter()
+                .filter_map(|file| std::fs::metadata(project_path.join(&file.path)).ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+    })
+    .await
+}
+
+/// The served index directory is renamed here while a rebuild writes a new
+/// one, and stays as a one-generation backup once the rebuild succeeds.
+/// Relative to the project root.
+pub const BACKUP_DIR_NAME: &str = ".adi.bak";
+
+/// Builds a fresh Adi on the index pool and swaps it in on success. The
+/// served index is renamed aside for the build and renamed back if it fails,
+/// so the index being served is never left half-built.
+async fn reindex(state: &AppState) -> anyhow::Result<adi_core::IndexProgress> {
+    let previous = loaded_snapshot(state).await;
+
+    let project_path = state.project_path();
+    let index_dir = project_path.join(INDEX_DIR_NAME);
+    let backup_dir = project_path.join(BACKUP_DIR_NAME);
+    // A new Adi rather than the loaded one: indexing updates an instance in
+    // place, which would change what is served mid-run. The loaded one keeps
+    // serving meanwhile and is kept, not reopened, if the build fails.
+    let (adi, progress) =
+        swap_index_dir(&index_dir, &backup_dir, build(state, &project_path)).await?;
+
+    install(state, adi, &progress, previous, None).await;
+    Ok(progress)
+}
+
+async fn index_upload(
+    state: &AppState,
+    dir: tempfile::TempDir,
+) -> anyhow::Result<adi_core::IndexProgress> {
+    let previous = loaded_snapshot(state).await;
+    let (adi, progress) = build(state, dir.path()).await?;
+
+    install(state, adi, &progress, previous, Some(dir)).await;
+    Ok(progress)
+}
+
+async fn loaded_snapshot(state: &AppState) -> Option<Snapshot> {
+    let loaded = state.adi.read().await.clone();
+    snapshot(loaded?).await
+}
+
+/// Serves a freshly built `adi`, switching to `upload` as the project root
+/// when given, and tells subscribers.
+async fn install(
+    state: &AppState,
+    adi: Arc<adi_core::Adi>,
+    progress: &adi_core::IndexProgress,
+    previous: Option<Snapshot>,
+    upload: Option<tempfile::TempDir>,
+) {
+    let event = match adi.status() {
+        Ok(status) => IndexUpdated::new(status.indexed_files, status.indexed_symbols),
+        Err(_) => IndexUpdated::new(progress.files_processed, progress.symbols_indexed),
+    };
+
+    // Canonical like the startup path, so path checks compare like with like
+    let root = match &upload {
+        Some(upload) => upload
+            .path()
+            .canonicalize()
+            .unwrap_or_else(|_| upload.path().to_path_buf()),
+        None => state.project_path(),
+    };
+    schema::record(&root).await;
+
+    let mut live = state.adi.write().await;
+    *live = Some(adi);
+    state.generation.fetch_add(1, Ordering::Release);
+    if let Some(upload) = upload {
+        *state.project_path.write().unwrap() = root;
+        // Dropping the previous upload deletes it
+        *state.upload.lock().unwrap() = Some(upload);
+    }
+    drop(live);
+
+    state.cache.clear();
+    state.freshness.built();
+    if let Some(previous) = previous {
+        *state.previous_index.lock().unwrap() = Some(previous);
+    }
+    events::publish(&state.events, event);
+}
+
+/// Opens a new Adi for `project_path` and indexes it on the index pool.
+async fn build(
+    state: &AppState,
+    project_path: &Path,
+) -> anyhow::Result<(Arc<adi_core::Adi>, adi_core::IndexProgress)> {
+    let adi = Arc::new(adi_core::Adi::open(project_path).await?);
+
+    let span = tracing::info_span!("index", project = %project_path.display());
+    let indexing = adi.clone();
+    let job = state
+        .index_pool
+        .spawn(async move { indexing.index().instrument(span).await });
+    let progress = job.await??;
+
+    Ok((adi, progress))
+}
+
+/// Runs `build`, which writes a new index to `index_dir`, with the index
+/// there renamed to `backup_dir` meanwhile. Renaming leaves files the live
+/// Adi holds open intact, so it keeps serving throughout. On failure the
+/// partial build is removed and the old index renamed back.
+async fn swap_index_dir<T>(
+    index_dir: &Path,
+    backup_dir: &Path,
+    build: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let set_aside = index_dir.is_dir();
+    if set_aside {
+        if backup_dir.exists() {
+            tokio::fs::remove_dir_all(backup_dir).await?;
+        }
+        tokio::fs::rename(index_dir, backup_dir).await?;
+    }
+
+    let error = match build.await {
+        Ok(built) => return Ok(built),
+        Err(e) if set_aside => e,
+        Err(e) => return Err(e),
+    };
+    if index_dir.exists() {
+        if let Err(e) = tokio::fs::remove_dir_all(index_dir).await {
+            tracing::error!("Failed to remove the partial index: {}", e);
+        }
+    }
+    match tokio::fs::rename(backup_dir, index_dir).await {
+        Ok(()) => Err(anyhow::anyhow!(
+            "{:#}; the previous index is still served",
+            error
+        )),
+        Err(e) => {
+            tracing::error!("Failed to move the previous index back: {}", e);
+            Err(error)
+        }
+    }
+}
+
+/// The index run in flight and how long the last one took, for telling
+/// clients how long to wait.
+#[derive(Default)]
+pub struct JobTracker {
+    /// Held for a whole run, so two never rename `.adi` and `.adi.bak` at once
+    turn: tokio::sync::Mutex<()>,
+    next_id: AtomicU64,
+    current: Mutex<Option<Job>>,
+    last_duration: Mutex<Option<Duration>>,
+    last_errors: Mutex<Option<IndexErrors>>,
+    last_throughput: Mutex<Option<Throughput>>,
+}
+
+/// How fast a finished run went, for comparing `INDEX_THREADS` settings.
+/// Measured over the whole run, backup and swap included.
+#[derive(Clone, Copy, Serialize)]
+pub struct Throughput {
+    pub job_id: u64,
+    pub duration_ms: u64,
+    pub files: u64,
+    /// Size on disk of the indexed files
+    pub bytes: u64,
+    pub files_per_sec: f64,
+    pub bytes_per_sec: f64,
+}
+
+impl Throughput {
+    fn new(job_id: u64, files: u64, bytes: u64, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        Self {
+            job_id,
+            duration_ms: elapsed.as_millis() as u64,
+            files,
+            bytes,
+            files_per_sec: files as f64 / secs,
+            bytes_per_sec: bytes as f64 / secs,
+        }
+    }
+}
+
+/// Files the last successful run couldn't index, as adi_core reported them.
+#[derive(Clone, Serialize)]
+pub struct IndexErrors {
+    pub job_id: u64,
+    pub finished_at_ms: u64,
+    pub errors: Vec<String>,
+}
+
+/// A run's hold on the index directory; the next run starts once it's dropped.
+struct Turn<'a> {
+    job: u64,
+    _held: tokio::sync::MutexGuard<'a, ()>,
+}
+
+#[derive(Clone, Copy)]
+struct Job {
+    id: u64,
+    started: Instant,
+    started_at_ms: u64,
+}
+
+/// A running job as reported to clients.
+#[derive(Serialize)]
+pub struct JobReport {
+    pub job_id: u64,
+    pub started_at_ms: u64,
+    /// Estimated from the previous run's duration; `null` without one
+    pub progress_percent: Option<u8>,
+    pub eta_ms: Option<u64>,
+}
+
+impl JobTracker {
+    /// Waits for the run in progress, if any, then starts the next job.
+    async fn wait_turn(&self) -> Turn<'_> {
+        let held = self.turn.lock().await;
+        Turn {
+            job: self.start(),
+            _held: held,
+        }
+    }
+
+    /// Starts the next job, or hands back the running one while another run
+    /// holds the turn. `None` only in the instant that run is starting up.
+    fn try_turn(&self) -> Result<Turn<'_>, Option<JobReport>> {
+        let held = self.turn.try_lock().map_err(|_| self.current())?;
+        Ok(Turn {
+            job: self.start(),
+            _held: held,
+        })
+    }
+
+    fn start(&self) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        *self.current.lock().unwrap() = Some(Job {
+            id,
+            started: Instant::now(),
+            started_at_ms: events::now_ms(),
+        });
+        id
+    }
+
+    fn finish(&self, id: u64, succeeded: bool) {
+        let mut current = self.current.lock().unwrap();
+        let Some(job) = current.filter(|job| job.id == id) else {
+            return;
+        };
+        *current = None;
+        if succeeded {
+            *self.last_duration.lock().unwrap() = Some(job.started.elapsed());
+        }
+    }
+
+    fn record_errors(&self, id: u64, errors: &[String]) {
+        *self.last_errors.lock().unwrap() = Some(IndexErrors {
+            job_id: id,
+            finished_at_ms: events::now_ms(),
+            errors: errors.to_vec(),
+        });
+    }
+
+    pub fn last_errors(&self) -> Option<IndexErrors> {
+        self.last_errors.lock().unwrap().clone()
+    }
+
+    fn record_throughput(&self, throughput: Throughput) {
+        *self.last_throughput.lock().unwrap() = Some(throughput);
+    }
+
+    /// Rates of the last successful run; `None` before one.
+    pub fn last_throughput(&self) -> Option<Throughput> {
+        *self.last_throughput.lock().unwrap()
+    }
+
+    pub fn current(&self) -> Option<JobReport> {
+        let job = (*self.current.lock().unwrap())?;
+        let elapsed = job.started.elapsed();
+        let expected = *self.last_duration.lock().unwrap();
+
+        Some(JobReport {
+            job_id: job.id,
+            started_at_ms: job.started_at_ms,
+            progress_percent: expected.map(|expected| {
+                let ratio = elapsed.as_secs_f64() / expected.as_secs_f64().max(f64::EPSILON);
+                // Never claim completion before the job actually finishes
+                (ratio * 100.0).min(99.0) as u8
+            }),
+            eta_ms: expected.map(|expected| expected.saturating_sub(elapsed).as_millis() as u64),
+        })
+    }
+}
+
+/// `503` for read endpoints while no index is loaded, saying what to expect.
+pub fn not_initialized(state: &AppState) -> (StatusCode, Json<serde_json::Value>) {
+    let body = match state.jobs.current() {
+        Some(job) => serde_json::json!({ "error": "ADI not initialized", "indexing": job }),
+        None => serde_json::json!({
+            "error": "ADI not initialized",
+            "hint": "POST /admin/index to build the index",
+        }),
+    };
+    (StatusCode::SERVICE_UNAVAILABLE, Json(body))
+}
+
+/// The index's current symbol table, kept for `/symbols/:id/diff`.
+async fn snapshot(adi: Arc<adi_core::Adi>) -> Option<Snapshot> {
+    match tokio::task::spawn_blocking(move || Snapshot::of(&adi)).await {
+        Ok(Ok(snapshot)) => Some(snapshot),
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to snapshot the previous index: {}", e);
+            None
+        }
+        Err(e) => {
+            tracing::warn!("Failed to snapshot the previous index: {}", e);
+            None
+        }
+    }
+}
+
+/// Dedicated runtime for `adi.index()`, so indexing parallelism is bounded
+/// by `INDEX_THREADS` instead of competing with request handling.
+pub struct IndexPool {
+    runtime: Option<Runtime>,
+}
+
+impl IndexPool {
+    pub fn new(threads: usize) -> std::io::Result<Self> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(threads)
+            .max_blocking_threads(threads)
+            .thread_name("adi-index")
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            runtime: Some(runtime),
+        })
+    }
+
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.runtime
+            .as_ref()
+            .expect("index runtime is only taken on drop")
+            .spawn(future)
+    }
+}
+
+impl Drop for IndexPool {
+    fn drop(&mut self) {
+        // Dropping a runtime from within async context panics; detach instead
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    /// A project whose served index holds one file reading `old`.
+    fn served_project() -> (tempfile::TempDir, std::path::PathBuf, std::path::PathBuf) {
+        let project = tempfile::tempdir().unwrap();
+        let index_dir = project.path().join(INDEX_DIR_NAME);
+        let backup_dir = project.path().join(BACKUP_DIR_NAME);
+        std::fs::create_dir(&index_dir).unwrap();
+        std::fs::write(index_dir.join("index.db"), "old").unwrap();
+        (project, index_dir, backup_dir)
+    }
+
+    /// Writes part of a new index, then fails like a crash mid-run.
+    async fn failing_build(index_dir: std::path::PathBuf) -> anyhow::Result<()> {
+        std::fs::create_dir(&index_dir)?;
+        std::fs::write(index_dir.join("index.db"), "partial")?;
+        Err(anyhow::anyhow!("indexing failed halfway"))
+    }
+
+    #[tokio::test]
+    async fn failed_build_keeps_the_old_index_serving() {
+        let (_project, index_dir, backup_dir) = served_project();
+        // Stands in for the database the live Adi holds open
+        let mut live = std::fs::File::open(index_dir.join("index.db")).unwrap();
+
+        let outcome =
+            swap_index_dir(&index_dir, &backup_dir, failing_build(index_dir.clone())).await;
+
+        let error = outcome.unwrap_err().to_string();
+        assert!(error.contains("indexing failed halfway"), "{}", error);
+        assert!(
+            error.contains("previous index is still served"),
+            "{}",
+            error
+        );
+        let mut served = String::new();
+        live.read_to_string(&mut served).unwrap();
+        assert_eq!(served, "old");
+        assert_eq!(
+            std::fs::read_to_string(index_dir.join("index.db")).unwrap(),
+            "old"
+        );
+        assert!(!backup_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn successful_build_keeps_the_old_index_as_backup() {
+        let (_project, index_dir, backup_dir) = served_project();
+        let target = index_dir.clone();
+
+        swap_index_dir(&index_dir, &backup_dir, async move {
+            std::fs::create_dir(&target)?;
+            std::fs::write(target.join("index.db"), "new")?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(index_dir.join("index.db")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            std::fs::read_to_string(backup_dir.join("index.db")).unwrap(),
+            "old"
+        );
+    }
+
+    #[tokio::test]
+    async fn successful_build_replaces_an_older_backup() {
+        let (_project, index_dir, backup_dir) = served_project();
+        std::fs::create_dir(&backup_dir).unwrap();
+        std::fs::write(backup_dir.join("index.db"), "older").unwrap();
+        let target = index_dir.clone();
+
+        swap_index_dir(&index_dir, &backup_dir, async move {
+            std::fs::create_dir(&target)?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(backup_dir.join("index.db")).unwrap(),
+            "old"
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_first_build_reports_the_error_alone() {
+        let project = tempfile::tempdir().unwrap();
+        let index_dir = project.path().join(INDEX_DIR_NAME);
+        let backup_dir = project.path().join(BACKUP_DIR_NAME);
+
+        let outcome =
+            swap_index_dir(&index_dir, &backup_dir, failing_build(index_dir.clone())).await;
+
+        assert_eq!(outcome.unwrap_err().to_string(), "indexing failed halfway");
+        assert!(!backup_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn overlapping_runs_keep_the_previous_index_as_backup() {
+        let (_project, index_dir, backup_dir) = served_project();
+        let jobs = JobTracker::default();
+        let run = |name: &'static str| {
+            let (jobs, index_dir, backup_dir) = (&jobs, &index_dir, &backup_dir);
+            async move {
+                let _turn = jobs.wait_turn().await;
+                swap_index_dir(index_dir, backup_dir, async move {
+                    std::fs::create_dir(index_dir)?;
+                    // Mid-build, where the other run would set this one aside
+                    tokio::task::yield_now().await;
+                    std::fs::write(index_dir.join("index.db"), name)?;
+                    Ok(())
+                })
+                .await
+            }
+        };
+
+        let (first, second) = tokio::join!(run("first"), run("second"));
+
+        first.unwrap();
+        second.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(index_dir.join("index.db")).unwrap(),
+            "second"
+        );
+        assert_eq!(
+            std::fs::read_to_string(backup_dir.join("index.db")).unwrap(),
+            "first"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_second_run_is_refused_with_the_running_job() {
+        let jobs = JobTracker::default();
+        let running = jobs.wait_turn().await;
+
+        let refused = jobs.try_turn().err().flatten().map(|job| job.job_id);
+        assert_eq!(refused, Some(running.job));
+
+        jobs.finish(running.job, true);
+        drop(running);
+        assert!(jobs.try_turn().is_ok());
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Symbol locations in LSP's shapes: `Location` for `?format=lsp`, and
+//! `DocumentSymbol`'s ranges for folding.
+
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Serialize)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Zero-based, as in LSP.
+#[derive(Clone, Copy, Serialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+impl Location {
+    /// `symbol`'s definition as a `file://` URI under `project_path`.
+    pub fn of(project_path: &Path, symbol: &adi_core::Symbol) -> Self {
+        Self {
+            uri: file_uri(&project_path.join(&symbol.file_path)),
+            range: Range::of(symbol),
+        }
+    }
+}
+
+impl Range {
+    /// The whole definition. adi_core counts lines from 1 and columns from 0,
+    /// in bytes.
+    pub fn of(symbol: &adi_core::Symbol) -> Self {
+        let location = &symbol.location;
+        Range {
+            start: Position {
+                line: location.start_line.saturating_sub(1),
+                character: location.start_col,
+            },
+            end: Position {
+                line: location.end_line.saturating_sub(1),
+                character: location.end_col,
+            },
+        }
+    }
+}
+
+/// The two ranges of LSP's `DocumentSymbol`: what the symbol spans, for
+/// folding, and its name within that, for selection.
+#[derive(Serialize)]
+pub struct Ranges {
+    pub full_range: Range,
+    pub selection_range: Range,
+}
+
+impl Ranges {
+    /// adi_core doesn't locate names, so the selection is the first
+    /// occurrence of the name in the definition's source, taken from
+    /// `content`, the symbol's file. Without one it is the full range, and
+    /// columns are adi_core's byte offsets rather than UTF-16 code units.
+    pub fn of(symbol: &adi_core::Symbol, content: Option<&[u8]>) -> Self {
+        let mut full_range = Range::of(symbol);
+        let location = &symbol.location;
+        let Some(content) = content.filter(|content| {
+            location.start_byte <= location.end_byte && location.end_byte <= content.len()
+        }) else {
+            return Self {
+                full_range,
+                selection_range: full_range,
+            };
+        };
+
+        full_range.start.character = utf16_column(content, location.start_byte);
+        full_range.end.character = utf16_column(content, location.end_byte);
+
+        let name = symbol.name.as_bytes();
+        let span = &content[location.start_byte..location.end_byte];
+        let found = (!name.is_empty())
+            .then(|| span.windows(name.len()).position(|w| w == name))
+            .flatten();
+        let Some(at) = found else {
+            return Self {
+                full_range,
+                selection_range: full_range,
+            };
+        };
+
+        let start = Position {
+            line: full_range.start.line + span[..at].iter().filter(|&&b| b == b'\n').count() as u32,
+            character: utf16_column(content, location.start_byte + at),
+        };
+        let end = Position {
+            line: start.line,
+            character: start.character + symbol.name.encode_utf16().count() as u32,
+        };
+        Self {
+            full_range,
+            selection_range: Range { start, end },
+        }
+    }
+}
+
+/// LSP's `character` for byte offset `at` in `content`: UTF-16 code units
+/// from the start of its line.
+fn utf16_column(content: &[u8], at: usize) -> u32 {
+    let line_start = content[..at]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |newline| newline + 1);
+    String::from_utf8_lossy(&content[line_start..at])
+        .encode_utf16()
+        .count() as u32
+}
+
+/// `file://` URI for an absolute path, percent-encoding everything but
+/// unreserved characters and separators.
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_column_counts_from_the_line_start() {
+        let content = b"fn a() {}\nfn b() {}";
+        assert_eq!(utf16_column(content, 0), 0);
+        assert_eq!(utf16_column(content, 3), 3);
+        assert_eq!(utf16_column(content, 13), 3);
+        assert_eq!(utf16_column(content, 10), 0);
+    }
+
+    #[test]
+    fn utf16_column_counts_code_units_not_bytes() {
+        // "é" is 2 bytes and 1 code unit; "😀" is 4 bytes and 2 code units
+        let content = "let é = 1; let 😀x = 2;".as_bytes();
+        let e = content.iter().position(|&b| b == b'=').unwrap();
+        assert_eq!(utf16_column(content, e), 6);
+        let x = content.iter().position(|&b| b == b'x').unwrap();
+        assert_eq!(utf16_column(content, x), 17);
+    }
+}
@@ -2,38 +2,281 @@
 // SPDX-License-Identifier: BSL-1.1
 // See LICENSE file for details
 
-use anyhow::Result;
+mod admin;
+mod blocking;
+mod cache;
+mod catalog;
+mod config;
+mod dedup;
+mod events;
+mod files;
+mod format;
+mod freshness;
+mod git;
+mod highlight;
+mod history;
+mod indexing;
+mod lsp;
+mod matching;
+mod packages;
+mod paging;
+mod preview;
+mod ranking;
+mod schema;
+mod server;
+mod stats;
+mod suggest;
+mod symbols;
+mod syntax;
+mod telemetry;
+mod tree;
+mod ui;
+mod upload;
+mod version;
+mod watchdog;
+mod watcher;
+mod webhook;
+
+use anyhow::{bail, Context, Result};
 use axum::{
+    body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, HeaderMap, StatusCode, Uri},
+    middleware,
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
+use config::Config;
+use futures_util::StreamExt;
+use indexing::IndexPool;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock, Semaphore};
+use tower::limit::ConcurrencyLimitLayer;
+use tower::Layer;
 use tower_http::cors::CorsLayer;
+use tower_http::normalize_path::NormalizePathLayer;
 use tower_http::trace::TraceLayer;
 use tracing::info;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use webhook::Webhook;
 
 struct AppState {
-    adi: RwLock<Option<adi_core::Adi>>,
-    project_path: PathBuf,
+    /// Handlers clone the `Arc` out instead of holding the guard while they work
+    adi: RwLock<Option<Arc<adi_core::Adi>>>,
+    /// Replaced when an uploaded snapshot is indexed; see [`AppState::project_path`]
+    project_path: std::sync::RwLock<PathBuf>,
+    /// Extracted upload being served, deleted once replaced
+    upload: std::sync::Mutex<Option<tempfile::TempDir>>,
+    config: Config,
+    index_pool: IndexPool,
+    webhook: Option<Webhook>,
+    events: broadcast::Sender<events::IndexUpdated>,
+    /// Requests running or waiting for a concurrency slot
+    admission: Semaphore,
+    cache: cache::IndexCache,
+    lock_health: watchdog::LockHealth,
+    /// Symbols as they were before the last reindex
+    previous_index: std::sync::Mutex<Option<history::Snapshot>>,
+    jobs: indexing::JobTracker,
+    /// Detected once at startup; git-backed features return `400` without it
+    git_repo: bool,
+    idempotency: admin::IdempotencyKeys,
+    /// File changes seen by the watcher since the last reindex
+    changes: watcher::ChangeQueue,
+    freshness: freshness::Freshness,
+    /// Bumped whenever a new index is swapped in; see [`server::index_generation`]
+    generation: std::sync::atomic::AtomicU64,
+}
+
+impl AppState {
+    /// Root of the sources being served: the project given at startup, or
+    /// the last uploaded snapshot. Always canonical, symlinks resolved.
+    fn project_path(&self) -> PathBuf {
+        self.project_path.read().unwrap().clone()
+    }
+
+    /// The loaded index, cloned out so the lock isn't held while it's used.
+    /// `503` once `LOCK_TIMEOUT_MS` passes without the lock, instead of
+    /// queueing the request behind a stuck holder.
+    async fn read_adi(
+        &self,
+    ) -> Result<Option<Arc<adi_core::Adi>>, (StatusCode, Json<serde_json::Value>)> {
+        self.read_adi_at().await.map(|(adi, _)| adi)
+    }
+
+    /// [`AppState::read_adi`] plus the generation that index was swapped in
+    /// at, for caches that must not outlive it.
+    async fn read_adi_at(
+        &self,
+    ) -> Result<(Option<Arc<adi_core::Adi>>, u64), (StatusCode, Json<serde_json::Value>)> {
+        let adi = match self.config.lock_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.adi.read())
+                .await
+                .map_err(|_| server::index_busy(self, timeout))?,
+            None => self.adi.read().await,
+        };
+        // Swaps bump the generation under the write lock, so this one is adi's
+        let generation = self.generation.load(std::sync::atomic::Ordering::Acquire);
+        Ok((adi.clone(), generation))
+    }
 }
 
 #[derive(Deserialize)]
 struct SearchQuery {
     q: String,
-    #[serde(default = "default_limit")]
-    limit: usize,
+    /// Falls back to the endpoint's configured default
+    limit: Option<usize>,
+    /// Soft deadline for `/search`; on expiry the response is marked truncated
+    deadline_ms: Option<u64>,
+    /// Collapse hits on the same symbol, listing the rest under `also_at`
+    #[serde(default)]
+    dedup: bool,
+    /// Replace each snippet with this many lines either side of the symbol
+    context_lines: Option<usize>,
+    /// Markers wrapped around matches in `context`, e.g. `<mark>`
+    highlight_pre: Option<String>,
+    highlight_post: Option<String>,
+    /// Score bonuses for matches in the name, file path and docs
+    weight_name: Option<f32>,
+    weight_path: Option<f32>,
+    weight_doc: Option<f32>,
+    /// Attach each result's score breakdown under `explain`
+    #[serde(default)]
+    explain: bool,
+    /// Drop results whose name or path matches the query only ignoring case
+    #[serde(default)]
+    case_sensitive: bool,
+    /// Only results in files modified since then: RFC 3339, or relative like `7d`
+    modified_after: Option<String>,
+}
+
+/// Most queries accepted by one `/search/batch` request.
+const MAX_BATCH_QUERIES: usize = 50;
+
+/// Queries of one `/search/batch` request run at once.
+const BATCH_PARALLELISM: usize = 4;
+
+#[derive(Deserialize)]
+struct SearchBatch {
+    queries: Vec<BatchQuery>,
+}
+
+#[derive(Deserialize)]
+struct BatchQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct CountByQuery {
+    q: String,
+    /// `kind`, `language` or `file`
+    by: String,
+}
+
+#[derive(Deserialize)]
+struct SymbolQuery {
+    #[serde(default)]
+    q: String,
+    /// Falls back to the endpoint's configured default
+    limit: Option<usize>,
+    /// Match parameter and return types instead of (or as well as) the name
+    signature: Option<String>,
+    language: Option<String>,
+    /// Only symbols of this kind, e.g. `struct`
+    kind: Option<String>,
+    /// Set to choose explicitly between substring (`false`) and edit-distance
+    /// (`true`) name matching; unset keeps adi_core's ranking
+    fuzzy: Option<bool>,
+    /// Drop symbols whose name matches `q` only ignoring case
+    #[serde(default)]
+    case_sensitive: bool,
+    /// Maximum edits for a fuzzy match
+    #[serde(default = "default_fuzzy_distance")]
+    fuzzy_distance: usize,
+    /// Results to skip before the page starts
+    #[serde(default)]
+    offset: usize,
+    /// Wrap the page with `offset`, `has_more` and `total`
+    #[serde(default)]
+    verbose: bool,
+    /// `relevance` (default) or `source`, by file then position
+    sort: Option<String>,
+    /// `contains`, `prefix`, `suffix` or `exact`, applied to the name
+    #[serde(rename = "match")]
+    name_match: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SymbolDetailQuery {
+    /// Include the definition's source text
+    #[serde(default)]
+    with_source: bool,
+    /// Include this many lines either side of the definition as `context`
+    context_lines: Option<usize>,
+    /// `lsp` answers with an LSP `Location` instead of the symbol
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FileQuery {
+    q: String,
+    /// Falls back to the endpoint's configured default
+    limit: Option<usize>,
+    /// Count matching lines in each file (reads every result from disk)
+    #[serde(default)]
+    with_matches: bool,
+    /// Drop files whose path matches `q` only ignoring case
+    #[serde(default)]
+    case_sensitive: bool,
+    /// Results to skip before the page starts
+    #[serde(default)]
+    offset: usize,
+    /// Wrap the page with `offset`, `has_more` and `total`
+    #[serde(default)]
+    verbose: bool,
+    /// Add matching files per language, counted over every match
+    #[serde(default)]
+    facets: bool,
+    /// Only files modified since then: RFC 3339, or relative like `7d`
+    modified_after: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TreeQuery {
+    /// Add per-directory file and symbol counts
+    #[serde(default)]
+    counts: bool,
+    /// `crate` to nest files under the package owning them
+    group_by: Option<String>,
+    /// Send NDJSON nodes as they're walked instead of one document
+    #[serde(default)]
+    stream: bool,
+}
+
+/// Lines buffered ahead of a slow `/tree?stream=true` client.
+const TREE_STREAM_BUFFER: usize = 256;
+
+#[derive(Deserialize)]
+struct StatusQuery {
+    /// Add the commit checked out in the project (git repositories only)
+    #[serde(default)]
+    commit: bool,
+}
+
+#[derive(Deserialize)]
+struct ReadyQuery {
+    /// Also run a query against the index instead of only checking it's loaded
+    #[serde(default)]
+    deep: bool,
 }
 
-fn default_limit() -> usize {
-    10
+fn default_fuzzy_distance() -> usize {
+    2
 }
 
 #[derive(Serialize)]
@@ -52,66 +295,242 @@ struct SuccessResponse<T> {
 async fn main() -> Result<()> {
     // Parse args
     let args: Vec<String> = std::env::args().collect();
-    let project_path = if args.len() > 1 {
-        PathBuf::from(&args[1])
-    } else {
-        std::env::current_dir()?
-    };
-
-    let port: u16 = std::env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(3000);
+    let project_path = resolve_project_path(args.get(1).map(String::as_str))?;
 
-    // Setup logging
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    // Setup logging and optional OTLP export
+    let _telemetry = telemetry::init()?;
 
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(filter)
-        .init();
+    let config = Config::from_env();
 
     info!("Starting ADI HTTP server");
     info!("Project path: {}", project_path.display());
 
     // Initialize ADI
     let adi = match adi_core::Adi::open(&project_path).await {
-        Ok(adi) => Some(adi),
+        // Opening can succeed on a damaged index, so make sure it answers queries
+        Ok(adi) => match adi.status() {
+            Ok(_) => Some(Arc::new(adi)),
+            Err(e) => {
+                tracing::error!(
+                    "Existing index failed validation ({}); treating it as missing. POST /admin/index to rebuild.",
+                    e
+                );
+                None
+            }
+        },
         Err(e) => {
-            tracing::warn!("Failed to initialize ADI: {}. Run /index first.", e);
+            tracing::warn!("Failed to initialize ADI: {}. POST /admin/index first.", e);
             None
         }
     };
+    let needs_index = adi.is_none();
+
+    info!("Indexing threads: {}", config.index_threads);
+    let index_pool = IndexPool::new(config.index_threads)?;
+
+    let git_repo = git::is_repository(&project_path);
+    if !git_repo {
+        info!("Project is not a git repository; git-backed features are disabled");
+    }
+
+    let freshness = if needs_index {
+        freshness::Freshness::default()
+    } else {
+        freshness::Freshness::loaded(&project_path)
+    };
 
     let state = Arc::new(AppState {
         adi: RwLock::new(adi),
-        project_path: project_path.canonicalize()?,
+        project_path: std::sync::RwLock::new(project_path),
+        upload: Default::default(),
+        index_pool,
+        webhook: config.webhook_url.clone().map(Webhook::new),
+        events: events::channel(),
+        admission: Semaphore::new(config.max_concurrency + config.max_queue),
+        cache: Default::default(),
+        lock_health: Default::default(),
+        previous_index: Default::default(),
+        jobs: Default::default(),
+        git_repo,
+        idempotency: Default::default(),
+        changes: Default::default(),
+        freshness,
+        generation: Default::default(),
+        config,
     });
 
+    watchdog::spawn(state.clone());
+    if state.config.watch {
+        if let Err(e) = watcher::spawn(state.clone()) {
+            tracing::error!("Cannot watch the project for changes: {}", e);
+        }
+    }
+
+    if needs_index && state.config.auto_index {
+        info!("AUTO_INDEX set, building the index in the background");
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = indexing::run(&state).await {
+                tracing::error!("Automatic indexing failed: {}", e);
+            }
+        });
+    }
+
+    let ui = if state.config.ui_enabled {
+        Router::new().route("/ui", get(ui::page))
+    } else {
+        Router::new()
+    };
+
+    // Only responses made of symbols have their kinds renamed; files and
+    // raw sources pass through untouched, and trees, too large to buffer
+    // twice, name kinds as they are built
+    let symbol_routes = Router::new()
+        .route(
+            "/search",
+            get(search)
+                .layer(middleware::from_fn(format::csv))
+                .layer(middleware::from_fn(format::group_by_file))
+                .layer(middleware::from_fn(format::fields)),
+        )
+        .route("/search/batch", post(search_batch))
+        .route(
+            "/symbols",
+            get(search_symbols)
+                .layer(middleware::from_fn(format::csv))
+                .layer(middleware::from_fn(format::group_by_file))
+                .layer(middleware::from_fn(format::fields)),
+        )
+        .route("/symbols/resolve", get(symbols::resolve))
+        .route("/symbols/:id", get(get_symbol))
+        .route("/symbols/:id/similar", get(symbols::similar))
+        .route("/diff/symbols", get(symbols::file_diff))
+        .route_layer(middleware::from_fn(format::canonical_kinds));
+
     let app = Router::new()
         .route("/", get(health))
         .route("/health", get(health))
+        .route("/readyz", get(readyz))
         .route("/status", get(status))
-        .route("/index", post(index_project))
-        .route("/search", get(search))
-        .route("/symbols", get(search_symbols))
-        .route("/symbols/:id", get(get_symbol))
-        .route("/files", get(search_files))
+        .route("/version", get(version))
+        .route("/capabilities", get(capabilities))
+        .route("/stats", get(stats::stats))
+        .route("/events", get(events::events))
+        .nest("/admin", admin::router(state.clone()))
+        .route("/search/count-by", get(search_count_by))
+        .route("/symbols/kinds", get(symbols::kinds))
+        .route("/symbols/:id/diff", get(symbols::diff))
+        .route("/symbols/:id/highlight", get(syntax::symbol))
+        .route("/symbols/:id/blame", get(symbols::blame))
+        .route(
+            "/files",
+            get(search_files)
+                .layer(middleware::from_fn(format::csv))
+                .layer(middleware::from_fn(format::fields)),
+        )
+        .route("/files/batch", post(files::batch))
+        .route("/files/recent", get(files::recent))
         .route("/files/*path", get(get_file))
+        .route("/raw/*path", get(files::raw))
         .route("/tree", get(get_tree))
+        .route("/tree/node/:id/children", get(get_tree_children))
+        .merge(symbol_routes)
+        .merge(ui)
+        .fallback(not_found)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            freshness::check,
+        ))
+        .layer(middleware::from_fn(server::retry_after))
+        .layer(ConcurrencyLimitLayer::new(state.config.max_concurrency))
+        // Outside the limit so time spent waiting for a slot counts
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            server::deadline,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), server::admit))
+        // Outside admission, so oversized queries never take a slot
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            server::query_len,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            format::internal_errors,
+        ))
+        .layer(middleware::from_fn(format::pretty))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            server::index_generation,
+        ))
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::make_request_span)
+                .on_response(telemetry::on_response()),
+        )
+        .with_state(state.clone());
+
+    // axum adds `Allow` outside per-route layers, so wrap the whole router
+    let app = trim_trailing_slashes(app)
+        .layer(middleware::from_fn(format::method_not_allowed))
+        // Outside routing, so `/v1/...` is routed by its plain path
+        .layer(middleware::from_fn(version::negotiate))
+        .layer(middleware::from_fn(telemetry::access_log))
+        // Outermost, so the trace span and error bodies see the same id
+        .layer(middleware::from_fn(telemetry::request_id));
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let addr = SocketAddr::from(([0, 0, 0, 0], state.config.port));
     info!("Listening on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    server::serve(listener, app, &state.config).await;
 
     Ok(())
 }
 
+/// Routing happens before router layers run, so the trailing slash has to
+/// come off outside the router: `/symbols/` routes as `/symbols`.
+fn trim_trailing_slashes(app: Router) -> Router {
+    Router::new().fallback_service(NormalizePathLayer::trim_trailing_slash().layer(app))
+}
+
+/// Expands `~`, then checks the project path is an existing directory so a
+/// typo fails here with a clear message rather than later in adi_core.
+fn resolve_project_path(arg: Option<&str>) -> Result<PathBuf> {
+    let path = match arg {
+        Some("~") => home_dir()?,
+        Some(arg) => match arg.strip_prefix("~/") {
+            Some(rest) => home_dir()?.join(rest),
+            None => PathBuf::from(arg),
+        },
+        None => std::env::current_dir().context("Cannot determine the current directory")?,
+    };
+
+    if !path.exists() {
+        bail!(
+            "Project path {} does not exist. Usage: adi-http [PROJECT_PATH]",
+            path.display()
+        );
+    }
+    if !path.is_dir() {
+        bail!(
+            "Project path {} is not a directory. Pass the root of the project to index.",
+            path.display()
+        );
+    }
+
+    path.canonicalize()
+        .with_context(|| format!("Cannot resolve project path {}", path.display()))
+}
+
+fn home_dir() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .context("Cannot expand ~: HOME is not set")
+}
+
 async fn health() -> impl IntoResponse {
     Json(serde_json::json!({
         "status": "ok",
@@ -120,175 +539,1170 @@ async fn health() -> impl IntoResponse {
     }))
 }
 
-async fn status(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let adi = state.adi.read().await;
+async fn version() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "adi_core": schema::ADI_CORE_VERSION,
+        "index_schema": schema::INDEX_SCHEMA_VERSION,
+    }))
+}
+
+/// Which optional features are on and the limits requests must stay
+/// within, so clients can adapt before authenticating.
+async fn capabilities(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let config = &state.config;
+    Json(serde_json::json!({
+        "features": {
+            "admin": !config.read_only,
+            "admin_auth": config.admin_token.is_some(),
+            "read_only": config.read_only,
+            "auto_index": config.auto_index,
+            "watch": config.watch,
+            "ui": config.ui_enabled,
+            "h2c": config.h2c,
+            "webhook": config.webhook_url.is_some(),
+            "git": state.git_repo,
+            "debug_errors": config.debug_errors,
+            // TLS is left to a proxy in front
+            "tls": false,
+        },
+        "limits": {
+            "default_limit": {
+                "search": config.default_limits.search,
+                "symbols": config.default_limits.symbols,
+                "files": config.default_limits.files,
+            },
+            "max_window": paging::MAX_WINDOW,
+            "max_query_len": config.max_query_len,
+            "max_batch_queries": MAX_BATCH_QUERIES,
+            "max_batch_paths": files::MAX_BATCH_PATHS,
+            "max_context_lines": files::MAX_CONTEXT_LINES,
+            "max_upload_bytes": config.max_upload_bytes,
+            "max_request_deadline_ms": config.max_request_deadline.as_millis() as u64,
+            "lock_timeout_ms": config.lock_timeout.map(|t| t.as_millis() as u64),
+            "freshness_ttl_secs": config.freshness_ttl.map(|t| t.as_secs()),
+        },
+        "formats": ["json", "csv", "ndjson", "lsp"],
+        "upload_formats": ["tar.gz", "zip"],
+        "api_versions": version::SUPPORTED,
+    }))
+}
+
+/// Routes offered as `did_you_mean` for unknown paths; keep in step with the
+/// router in `main`.
+const ROUTES: &[&str] = &[
+    "/health",
+    "/readyz",
+    "/status",
+    "/version",
+    "/capabilities",
+    "/stats",
+    "/events",
+    "/admin/index",
+    "/admin/index/upload",
+    "/admin/index/errors",
+    "/search",
+    "/search/batch",
+    "/search/count-by",
+    "/symbols",
+    "/symbols/kinds",
+    "/symbols/resolve",
+    "/symbols/:id",
+    "/symbols/:id/diff",
+    "/symbols/:id/similar",
+    "/symbols/:id/highlight",
+    "/symbols/:id/blame",
+    "/diff/symbols",
+    "/files",
+    "/files/batch",
+    "/files/recent",
+    "/files/*path",
+    "/raw/*path",
+    "/tree",
+    "/tree/node/:id/children",
+    "/ui",
+];
+
+async fn not_found(uri: Uri) -> impl IntoResponse {
+    let mut body = serde_json::json!({ "error": "not found" });
+    if let Some(route) = suggest::route(uri.path(), ROUTES) {
+        body["did_you_mean"] = route.into();
+    }
+    (StatusCode::NOT_FOUND, Json(body))
+}
+
+async fn readyz(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ReadyQuery>,
+) -> impl IntoResponse {
+    let lock = state.lock_health.report();
+    let not_ready = |error: String, lock| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "status": "not_ready", "error": error, "lock": lock })),
+        )
+    };
+
+    if !lock.healthy {
+        return not_ready("Index lock unavailable".to_string(), lock);
+    }
+
+    // Don't let a stuck lock hang the probe itself
+    let loaded = tokio::time::timeout(watchdog::ACQUIRE_TIMEOUT, state.adi.read()).await;
+    let Ok(adi) = loaded.map(|adi| adi.clone()) else {
+        return not_ready("Index lock unavailable".to_string(), lock);
+    };
 
     match adi.as_ref() {
-        Some(adi) => match adi.status() {
-            Ok(status) => (StatusCode::OK, Json(serde_json::to_value(status).unwrap())),
-            Err(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": e.to_string() })),
+        Some(adi) if query.deep => match blocking::query(adi, |adi| adi.status()).await {
+            Ok(_) => (
+                StatusCode::OK,
+                Json(serde_json::json!({ "status": "ready", "lock": lock })),
             ),
+            Err(e) => not_ready(e.to_string(), lock),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "ADI not initialized. POST /index first." })),
+        Some(_) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "status": "ready", "lock": lock })),
         ),
+        None => not_ready("ADI not initialized".to_string(), lock),
     }
 }
 
-async fn index_project(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    // Re-initialize ADI
-    let adi = match adi_core::Adi::open(&state.project_path).await {
+async fn status(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StatusQuery>,
+) -> impl IntoResponse {
+    if query.commit && !state.git_repo {
+        return git::not_a_repository("commit");
+    }
+
+    let adi = match state.read_adi().await {
         Ok(adi) => adi,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": e.to_string() })),
-            );
-        }
+        Err(rejection) => return rejection,
     };
 
-    // Index
-    let progress = match adi.index().await {
-        Ok(p) => p,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": e.to_string() })),
-            );
+    let mut body = match adi.as_ref() {
+        Some(adi) => match blocking::query(adi, |adi| adi.status()).await {
+            Ok(status) => {
+                let mut body = serde_json::to_value(status).unwrap();
+                body["initialized"] = true.into();
+                body
+            }
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": e.to_string() })),
+                );
+            }
+        },
+        // Same shape as a loaded index, so dashboards render zeros
+        None => {
+            let mut body = serde_json::to_value(adi_core::Status {
+                indexed_files: 0,
+                indexed_symbols: 0,
+                embedding_dimensions: 0,
+                embedding_model: String::new(),
+                last_indexed: None,
+                storage_size_bytes: 0,
+            })
+            .unwrap();
+            body["initialized"] = false.into();
+            body["indexing"] = serde_json::to_value(state.jobs.current()).unwrap();
+            body
         }
     };
 
-    // Store new ADI instance
-    *state.adi.write().await = Some(adi);
-
-    (StatusCode::OK, Json(serde_json::to_value(progress).unwrap()))
+    if query.commit {
+        body["commit"] = git::head_commit(&state.project_path()).await.into();
+    }
+    let schema = schema::check(&state.project_path()).await;
+    if !schema.compatible {
+        body["hint"] = "Index was written by an incompatible release; \
+                        POST /admin/index to rebuild it"
+            .into();
+    } else if adi.is_none() && state.jobs.current().is_none() {
+        body["hint"] = "POST /admin/index to build the index".into();
+    }
+    body["schema"] = serde_json::to_value(schema).unwrap();
+    if state.config.watch {
+        body["pending_changes"] = state.changes.pending().into();
+    }
+    (StatusCode::OK, Json(body))
 }
 
 async fn search(
     State(state): State<Arc<AppState>>,
     Query(query): Query<SearchQuery>,
 ) -> impl IntoResponse {
-    let adi = state.adi.read().await;
+    if let Err(rejection) = files::check_context_lines(query.context_lines) {
+        return rejection;
+    }
+    let modified_after = match query.modified_after.as_deref() {
+        Some(value) => match files::parse_modified_after(value) {
+            Ok(since) => Some(since),
+            Err(rejection) => return rejection,
+        },
+        None => None,
+    };
+    let defaults = state.config.search_weights;
+    let weights = ranking::Weights {
+        name: query.weight_name.unwrap_or(defaults.name),
+        path: query.weight_path.unwrap_or(defaults.path),
+        doc: query.weight_doc.unwrap_or(defaults.doc),
+    };
+    if ![weights.name, weights.path, weights.doc]
+        .into_iter()
+        .all(ranking::valid_weight)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Weights must be between 0 and {}", ranking::MAX_WEIGHT)
+            })),
+        );
+    }
+
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
 
     match adi.as_ref() {
-        Some(adi) => match adi.search(&query.q, query.limit).await {
-            Ok(results) => (StatusCode::OK, Json(serde_json::to_value(results).unwrap())),
-            Err(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": e.to_string() })),
-            ),
+        Some(adi) => {
+            let limit = query.limit.unwrap_or(state.config.default_limits.search);
+            let project_path = state.project_path();
+            let soft_deadline = query.deadline_ms.is_some();
+            let (searched, truncated) = match query.deadline_ms {
+                Some(ms) => {
+                    let gathered = std::sync::Mutex::new(Vec::new());
+                    let search = search_filtered(
+                        adi,
+                        &project_path,
+                        &query,
+                        modified_after,
+                        limit,
+                        Some(&gathered),
+                    );
+                    let finished = tokio::time::timeout(Duration::from_millis(ms), search).await;
+                    match finished {
+                        Ok(searched) => (searched, false),
+                        // Whatever the last round to finish before the deadline found
+                        Err(_) => (Ok(gathered.into_inner().unwrap_or_default()), true),
+                    }
+                }
+                None => {
+                    let searched =
+                        search_filtered(adi, &project_path, &query, modified_after, limit, None);
+                    (searched.await, false)
+                }
+            };
+            let mut outcome = searched.map(|mut results| -> Vec<dedup::Hit> {
+                ranking::rerank(&mut results, &query.q, &weights);
+                let mut hits: Vec<dedup::Hit> = if query.dedup {
+                    dedup::collapse(results)
+                } else {
+                    results.into_iter().map(dedup::Hit::from).collect()
+                };
+                if query.explain {
+                    for hit in hits.iter_mut() {
+                        let explanation = ranking::Explanation::of(&hit.result, &query.q, &weights);
+                        hit.explain = Some(explanation);
+                    }
+                }
+                hits
+            });
+            if let Ok(hits) = &mut outcome {
+                if let Some(lines) = query.context_lines {
+                    for hit in hits.iter_mut() {
+                        let symbol = &hit.result.symbol;
+                        let context =
+                            files::read_context(&state.project_path(), symbol, lines).await;
+                        hit.result.context = context.or(hit.result.context.take());
+                    }
+                }
+                highlight_hits(hits, &query);
+            }
+
+            match outcome {
+                // Only pay for suggestions when there is nothing else to show
+                Ok(results) if results.is_empty() && !truncated => {
+                    let q = query.q.clone();
+                    let suggestions =
+                        blocking::query(adi, move |adi| suggest::did_you_mean(adi, &q)).await;
+                    let mut body = serde_json::json!({
+                        "results": results,
+                        "suggestions": suggestions,
+                    });
+                    if soft_deadline {
+                        body["truncated"] = false.into();
+                    }
+                    (StatusCode::OK, Json(body))
+                }
+                Ok(results) if soft_deadline => (
+                    StatusCode::OK,
+                    Json(serde_json::json!({ "results": results, "truncated": truncated })),
+                ),
+                Ok(results) => (StatusCode::OK, Json(serde_json::to_value(results).unwrap())),
+                Err(e) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": e.to_string() })),
+                ),
+            }
+        }
+        None => indexing::not_initialized(&state),
+    }
+}
+
+/// Results asked for in the first round of a `/search` with a soft deadline.
+const FIRST_DEADLINE_ROUND: usize = 10;
+
+/// adi_core's search for `q` narrowed by the filters it can't apply itself,
+/// asking for more until `limit` results pass or it runs out.
+///
+/// With `gathered`, the search starts small and widens, leaving each round's
+/// results there as it finishes, so a caller that gives up early still has
+/// the best answer found so far.
+async fn search_filtered(
+    adi: &adi_core::Adi,
+    project_path: &std::path::Path,
+    query: &SearchQuery,
+    modified_after: Option<std::time::SystemTime>,
+    limit: usize,
+    gathered: Option<&std::sync::Mutex<Vec<adi_core::SearchResult>>>,
+) -> adi_core::Result<Vec<adi_core::SearchResult>> {
+    let mut asked = match gathered {
+        Some(_) => limit.min(FIRST_DEADLINE_ROUND),
+        None => limit,
+    };
+    loop {
+        let found = adi.search(&query.q, asked).await?;
+        let exhausted = found.len() < asked;
+        let mut results = Vec::with_capacity(found.len());
+        for result in found {
+            let symbol = &result.symbol;
+            let path = symbol.file_path.to_string_lossy();
+            if query.case_sensitive && !matching::respects_case(&query.q, &[&symbol.name, &path]) {
+                continue;
+            }
+            if let Some(since) = modified_after {
+                if !files::modified_since(project_path, &symbol.file_path, since).await {
+                    continue;
+                }
+            }
+            results.push(result);
+        }
+        match paging::widen_toward(asked, limit) {
+            Some(more) if results.len() < limit && !exhausted => {
+                if let Some(gathered) = gathered {
+                    if let Ok(mut gathered) = gathered.lock() {
+                        *gathered = results;
+                    }
+                }
+                asked = more;
+            }
+            _ => {
+                results.truncate(limit);
+                return Ok(results);
+            }
+        }
+    }
+}
+
+/// Runs several searches against the same index, answering each in order
+/// with its `results` or its own `error`.
+async fn search_batch(
+    State(state): State<Arc<AppState>>,
+    format::ApiJson(batch): format::ApiJson<SearchBatch>,
+) -> impl IntoResponse {
+    if batch.queries.len() > MAX_BATCH_QUERIES {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("At most {} queries per batch", MAX_BATCH_QUERIES)
+            })),
+        );
+    }
+
+    for query in &batch.queries {
+        if let Err(rejection) = server::check_query_len(&state, "q", Some(&query.q)) {
+            return rejection;
+        }
+    }
+
+    // Every query goes to this one index, even if a reindex swaps in another
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi else {
+        return indexing::not_initialized(&state);
+    };
+    let adi = &adi;
+
+    let weights = state.config.search_weights;
+    let default_limit = state.config.default_limits.search;
+    let searches = batch.queries.into_iter().map(|query| async move {
+        let limit = query.limit.unwrap_or(default_limit);
+        match adi.search(&query.q, limit).await {
+            Ok(mut results) => {
+                ranking::rerank(&mut results, &query.q, &weights);
+                serde_json::json!({ "q": query.q, "results": results })
+            }
+            Err(e) => serde_json::json!({ "q": query.q, "error": e.to_string() }),
+        }
+    });
+    let answers: Vec<serde_json::Value> = futures_util::stream::iter(searches)
+        .buffered(BATCH_PARALLELISM)
+        .collect()
+        .await;
+
+    (StatusCode::OK, Json(serde_json::Value::Array(answers)))
+}
+
+/// Number of `/search` matches per kind, language or file, counted over
+/// every match up to [`paging::MAX_WINDOW`] rather than a page.
+async fn search_count_by(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CountByQuery>,
+) -> impl IntoResponse {
+    let group: fn(&adi_core::Symbol) -> String = match query.by.as_str() {
+        "kind" => |symbol: &adi_core::Symbol| {
+            let raw = catalog::kind_name(&symbol.kind);
+            catalog::canonical_kind(&raw).map_or(raw, str::to_string)
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "ADI not initialized" })),
+        "language" => |symbol: &adi_core::Symbol| catalog::language_name(&symbol.language),
+        "file" => |symbol: &adi_core::Symbol| symbol.file_path.to_string_lossy().into_owned(),
+        other => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("Cannot count by {:?}: expected kind, language or file", other)
+                })),
+            );
+        }
+    };
+
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi.as_ref() else {
+        return indexing::not_initialized(&state);
+    };
+
+    match adi.search(&query.q, paging::MAX_WINDOW).await {
+        Ok(results) => {
+            let mut counts = std::collections::BTreeMap::new();
+            for result in &results {
+                *counts.entry(group(&result.symbol)).or_insert(0usize) += 1;
+            }
+            (StatusCode::OK, Json(serde_json::to_value(counts).unwrap()))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
         ),
     }
 }
 
+/// Marks query matches in each snippet, or reports their offsets when no
+/// markers were requested.
+fn highlight_hits(hits: &mut [dedup::Hit], query: &SearchQuery) {
+    let markers = (&query.highlight_pre, &query.highlight_post);
+    for hit in hits {
+        let Some(context) = hit.result.context.as_mut() else {
+            continue;
+        };
+        let spans = highlight::spans(context, &query.q);
+
+        match markers {
+            (None, None) => hit.highlights = Some(highlight::char_offsets(context, &spans)),
+            (pre, post) => {
+                let pre = pre.as_deref().unwrap_or_default();
+                let post = post.as_deref().unwrap_or_default();
+                *context = highlight::wrap(context, &spans, pre, post);
+            }
+        }
+    }
+}
+
 async fn search_symbols(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<SearchQuery>,
-) -> impl IntoResponse {
-    let adi = state.adi.read().await;
+    Query(query): Query<SymbolQuery>,
+) -> Response {
+    if query.signature.is_none() && query.q.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Missing query parameter: q or signature" })),
+        )
+            .into_response();
+    }
+    if let (Some(_), Some(language)) = (&query.signature, &query.language) {
+        if !catalog::has_type_info(language) {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("Signature search is not available for {}: no type information", language)
+                })),
+            )
+                .into_response();
+        }
+    }
+    let name_match = match query.name_match.as_deref().map(matching::NameMatch::parse) {
+        None => None,
+        Some(Some(mode)) => Some(mode),
+        Some(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!(
+                        "Unknown match mode {:?}: expected contains, prefix, suffix or exact",
+                        query.name_match.as_deref().unwrap_or_default()
+                    )
+                })),
+            )
+                .into_response();
+        }
+    };
+    if let Some(conflict) = filter_conflict(&query) {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": conflict })),
+        )
+            .into_response();
+    }
+
+    let by_source = match files::source_order(query.sort.as_deref()) {
+        Ok(by_source) => by_source,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let limit = query.limit.unwrap_or(state.config.default_limits.symbols);
+    let page = paging::Page {
+        offset: query.offset,
+        limit,
+    };
+    // Source order is over every match, not just the most relevant page
+    let fetch = if by_source {
+        paging::MAX_WINDOW
+    } else {
+        page.fetch()
+    };
+    let verbose = query.verbose;
+    if page.beyond_window() {
+        let paging = paging::Paging {
+            offset: page.offset,
+            has_more: false,
+            total: None,
+        };
+        return paging::respond(Vec::<adi_core::Symbol>::new(), &paging, verbose);
+    }
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection.into_response(),
+    };
 
     match adi.as_ref() {
-        Some(adi) => match adi.search_symbols(&query.q, query.limit).await {
-            Ok(results) => (StatusCode::OK, Json(serde_json::to_value(results).unwrap())),
-            Err(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": e.to_string() })),
-            ),
-        },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "ADI not initialized" })),
+        Some(adi) => {
+            let adi = adi.clone();
+            // Whole-index scans run off the runtime and stop if the client goes away
+            let results = match (query.signature.clone(), query.fuzzy) {
+                (Some(signature), _) => {
+                    blocking::run(move |cancel| {
+                        search_signatures(&adi, &query, name_match, &signature, fetch, cancel).map(
+                            |mut symbols| {
+                                if by_source {
+                                    files::sort_by_source(&mut symbols, |s| s);
+                                }
+                                paged(page, symbols)
+                            },
+                        )
+                    })
+                    .await
+                }
+                (None, Some(fuzzy)) => {
+                    blocking::run(move |_| {
+                        adi.get_tree().map(|tree| {
+                            let distance = fuzzy.then_some(query.fuzzy_distance);
+                            let include = |f: &adi_core::FileNode, s: &adi_core::SymbolNode| {
+                                language_matches(&query.language, &f.language)
+                                    && kind_matches(&query.kind, &s.kind)
+                                    && case_matches(&query, &s.name)
+                                    && mode_matches(&query, name_match, &s.name)
+                            };
+                            let mut matches = matching::match_names(
+                                &adi, &tree, &query.q, distance, fetch, include,
+                            );
+                            if by_source {
+                                files::sort_by_source(&mut matches, |m| &m.symbol);
+                            }
+                            paged(page, matches)
+                        })
+                    })
+                    .await
+                }
+                (None, None) => search_symbols_filtered(&adi, &query, name_match, fetch)
+                    .await
+                    .map(|(mut symbols, exhausted)| {
+                        if by_source {
+                            files::sort_by_source(&mut symbols, |s| s);
+                        }
+                        let (symbols, paging) = page.slice_filtered(symbols, exhausted);
+                        (serde_json::to_value(symbols).unwrap(), paging)
+                    }),
+            };
+
+            match results {
+                Ok((results, paging)) => paging::respond(results, &paging, verbose),
+                Err(e) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": e.to_string() })),
+                )
+                    .into_response(),
+            }
+        }
+        None => indexing::not_initialized(&state).into_response(),
+    }
+}
+
+fn paged<T: Serialize>(page: paging::Page, results: Vec<T>) -> (serde_json::Value, paging::Paging) {
+    let (results, paging) = page.slice(results);
+    (serde_json::to_value(results).unwrap(), paging)
+}
+
+/// adi_core's symbol search narrowed by the filters it can't apply itself,
+/// asking for more until `wanted` symbols pass or it runs out. The flag
+/// says whether it ran out, making the count the exact total.
+async fn search_symbols_filtered(
+    adi: &adi_core::Adi,
+    query: &SymbolQuery,
+    name_match: Option<matching::NameMatch>,
+    wanted: usize,
+) -> adi_core::Result<(Vec<adi_core::Symbol>, bool)> {
+    let mut asked = wanted;
+    loop {
+        let found = adi.search_symbols(&query.q, asked).await?;
+        let exhausted = found.len() < asked;
+        let symbols: Vec<_> = found
+            .into_iter()
+            .filter(|s| language_matches(&query.language, &s.language))
+            .filter(|s| kind_matches(&query.kind, &s.kind))
+            .filter(|s| case_matches(query, &s.name))
+            .filter(|s| mode_matches(query, name_match, &s.name))
+            .collect();
+        match paging::widen(asked) {
+            Some(more) if symbols.len() < wanted && !exhausted => asked = more,
+            _ => return Ok((symbols, exhausted)),
+        }
+    }
+}
+
+/// Type-directed search over files in languages that record signatures.
+fn search_signatures(
+    adi: &adi_core::Adi,
+    query: &SymbolQuery,
+    name_match: Option<matching::NameMatch>,
+    signature: &str,
+    limit: usize,
+    cancel: &blocking::Cancellation,
+) -> adi_core::Result<Vec<adi_core::Symbol>> {
+    let mut tree = adi.get_tree()?;
+    tree.files.retain(|file| {
+        let language = catalog::language_name(&file.language);
+        catalog::has_type_info(&language) && language_matches(&query.language, &file.language)
+    });
+
+    let name = query.q.to_lowercase();
+    Ok(catalog::symbols(adi, &tree)
+        .take_while(|_| !cancel.is_cancelled())
+        .filter(|s| name.is_empty() || s.name.to_lowercase().contains(&name))
+        .filter(|s| case_matches(query, &s.name))
+        .filter(|s| mode_matches(query, name_match, &s.name))
+        .filter(|s| kind_matches(&query.kind, &s.kind))
+        .filter(|s| {
+            s.signature
+                .as_deref()
+                .is_some_and(|sig| catalog::signature_matches(sig, signature))
+        })
+        .take(limit)
+        .collect())
+}
+
+fn case_matches(query: &SymbolQuery, name: &str) -> bool {
+    !query.case_sensitive || matching::respects_case(&query.q, &[name])
+}
+
+/// Whether `name` passes `match`; everything does without one, or without `q`.
+fn mode_matches(query: &SymbolQuery, mode: Option<matching::NameMatch>, name: &str) -> bool {
+    match mode {
+        Some(mode) if !query.q.is_empty() => mode.matches(&query.q, name, query.case_sensitive),
+        _ => true,
+    }
+}
+
+/// Parameters that can't apply together, explained for a `422`.
+fn filter_conflict(query: &SymbolQuery) -> Option<String> {
+    match (&query.signature, &query.kind, query.fuzzy) {
+        (Some(_), Some(kind), _) if !catalog::is_callable(kind) => Some(format!(
+            "signature only applies to callables (function, method), not kind={}",
+            kind
+        )),
+        (Some(_), _, Some(_)) => Some(
+            "fuzzy applies to name matching; signature search always matches names by substring"
+                .to_string(),
+        ),
+        _ if query.name_match.is_some() && query.fuzzy == Some(true) => Some(
+            "fuzzy=true accepts names within edits of q, which no match mode allows".to_string(),
         ),
+        _ => None,
     }
 }
 
+fn kind_matches(filter: &Option<String>, kind: &adi_core::SymbolKind) -> bool {
+    filter
+        .as_deref()
+        .is_none_or(|wanted| catalog::kind_matches(wanted, kind))
+}
+
+fn language_matches(filter: &Option<String>, language: &adi_core::Language) -> bool {
+    filter
+        .as_deref()
+        .is_none_or(|wanted| catalog::language_name(language).eq_ignore_ascii_case(wanted))
+}
+
 async fn get_symbol(
     State(state): State<Arc<AppState>>,
     Path(id): Path<i64>,
+    Query(query): Query<SymbolDetailQuery>,
 ) -> impl IntoResponse {
-    let adi = state.adi.read().await;
+    if let Err(rejection) = files::check_context_lines(query.context_lines) {
+        return rejection;
+    }
+    let lsp = match query.format.as_deref() {
+        None => false,
+        Some("lsp") => true,
+        Some(other) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": format!("Unknown format: {}", other) })),
+            );
+        }
+    };
+
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
 
     match adi.as_ref() {
-        Some(adi) => match adi.get_symbol(adi_core::SymbolId(id)) {
-            Ok(symbol) => (StatusCode::OK, Json(serde_json::to_value(symbol).unwrap())),
+        Some(adi) => match blocking::query(adi, move |adi| adi.get_symbol(adi_core::SymbolId(id)))
+            .await
+        {
+            Ok(symbol) if lsp => {
+                let location = lsp::Location::of(&state.project_path(), &symbol);
+                (
+                    StatusCode::OK,
+                    Json(serde_json::to_value(location).unwrap()),
+                )
+            }
+            Ok(symbol) => {
+                let source = if query.with_source {
+                    files::read_source(&state.project_path(), &symbol).await
+                } else {
+                    None
+                };
+                let context = match query.context_lines {
+                    Some(lines) => files::read_context(&state.project_path(), &symbol, lines).await,
+                    None => None,
+                };
+
+                let content = tokio::fs::read(state.project_path().join(&symbol.file_path))
+                    .await
+                    .ok();
+                let ranges = lsp::Ranges::of(&symbol, content.as_deref());
+
+                let mut body = serde_json::to_value(symbol).unwrap();
+                body["full_range"] = serde_json::to_value(ranges.full_range).unwrap();
+                body["selection_range"] = serde_json::to_value(ranges.selection_range).unwrap();
+                if let Some(source) = source {
+                    body["source"] = source.into();
+                }
+                if let Some(context) = context {
+                    body["context"] = context.into();
+                }
+                (StatusCode::OK, Json(body))
+            }
             Err(e) => (
                 StatusCode::NOT_FOUND,
                 Json(serde_json::json!({ "error": e.to_string() })),
             ),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "ADI not initialized" })),
-        ),
+        None => indexing::not_initialized(&state),
     }
 }
 
 async fn search_files(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<SearchQuery>,
+    Query(query): Query<FileQuery>,
+) -> Response {
+    let limit = query.limit.unwrap_or(state.config.default_limits.files);
+    let page = paging::Page {
+        offset: query.offset,
+        limit,
+    };
+    let modified_after = match query.modified_after.as_deref() {
+        Some(value) => match files::parse_modified_after(value) {
+            Ok(since) => Some(since),
+            Err(rejection) => return rejection.into_response(),
+        },
+        None => None,
+    };
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    match adi.as_ref() {
+        Some(adi) => {
+            // Facets count the whole match set rather than the page
+            let fetch = if query.facets {
+                paging::MAX_WINDOW
+            } else {
+                page.fetch()
+            };
+            let project_path = state.project_path();
+            match search_files_filtered(adi, &project_path, &query, modified_after, fetch).await {
+                Ok((results, exhausted)) => {
+                    let facets = query.facets.then(|| {
+                        let mut counts = std::collections::BTreeMap::new();
+                        for file in &results {
+                            *counts
+                                .entry(catalog::language_name(&file.language))
+                                .or_insert(0) += 1;
+                        }
+                        counts
+                    });
+                    // Only the page is read from disk
+                    let (results, paging) = page.slice_filtered(results, exhausted);
+                    let match_query = query.with_matches.then_some(query.q.as_str());
+                    let mut hits = Vec::with_capacity(results.len());
+                    for file in results {
+                        let project_path = state.project_path();
+                        hits.push(
+                            files::FileHit::load(
+                                &project_path,
+                                file,
+                                match_query,
+                                query.case_sensitive,
+                            )
+                            .await,
+                        );
+                    }
+                    match facets {
+                        Some(facets) => {
+                            let mut extra = serde_json::Map::new();
+                            extra.insert("facets".into(), serde_json::to_value(facets).unwrap());
+                            paging::respond_with(hits, &paging, extra)
+                        }
+                        None => paging::respond(hits, &paging, query.verbose),
+                    }
+                }
+                Err(e) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": e.to_string() })),
+                )
+                    .into_response(),
+            }
+        }
+        None => indexing::not_initialized(&state).into_response(),
+    }
+}
+
+/// adi_core's file search narrowed by `case_sensitive` and `modified_after`,
+/// asking for more until `wanted` files pass or it runs out. The flag says
+/// whether it ran out, making the count the exact total.
+async fn search_files_filtered(
+    adi: &adi_core::Adi,
+    project_path: &std::path::Path,
+    query: &FileQuery,
+    modified_after: Option<std::time::SystemTime>,
+    wanted: usize,
+) -> adi_core::Result<(Vec<adi_core::File>, bool)> {
+    let mut asked = wanted;
+    loop {
+        let found = adi.search_files(&query.q, asked).await?;
+        let exhausted = found.len() < asked;
+        let mut passed = Vec::with_capacity(found.len());
+        for file in found {
+            let path = file.path.to_string_lossy();
+            if query.case_sensitive && !matching::respects_case(&query.q, &[&path]) {
+                continue;
+            }
+            if let Some(since) = modified_after {
+                if !files::modified_since(project_path, &file.path, since).await {
+                    continue;
+                }
+            }
+            passed.push(file);
+        }
+        match paging::widen(asked) {
+            Some(more) if passed.len() < wanted && !exhausted => asked = more,
+            _ => return Ok((passed, exhausted)),
+        }
+    }
+}
+
+async fn get_file(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<files::FileSymbolsQuery>,
 ) -> impl IntoResponse {
-    let adi = state.adi.read().await;
+    if let Some(id) = files::symbols_route(&path) {
+        let (status, Json(mut body)) = files::symbols_by_id(&state, id, &query).await;
+        format::canonicalize_kinds(&mut body);
+        return (status, Json(body));
+    }
 
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+
+    let file_path = PathBuf::from(&path);
     match adi.as_ref() {
-        Some(adi) => match adi.search_files(&query.q, query.limit).await {
-            Ok(results) => (StatusCode::OK, Json(serde_json::to_value(results).unwrap())),
+        Some(adi) => match blocking::query(adi, move |adi| adi.get_file(&file_path)).await {
+            Ok(file_info) => {
+                let mut body = serde_json::to_value(&file_info).unwrap();
+                body["modified_at"] =
+                    files::modified_at(&state.project_path(), &file_info.file.path)
+                        .await
+                        .into();
+                format::canonicalize_kinds(&mut body);
+                (StatusCode::OK, Json(body))
+            }
             Err(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::NOT_FOUND,
                 Json(serde_json::json!({ "error": e.to_string() })),
             ),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "ADI not initialized" })),
-        ),
+        None => indexing::not_initialized(&state),
     }
 }
 
-async fn get_file(
+async fn get_tree(
     State(state): State<Arc<AppState>>,
-    Path(path): Path<String>,
-) -> impl IntoResponse {
-    let adi = state.adi.read().await;
+    headers: HeaderMap,
+    Query(query): Query<TreeQuery>,
+) -> Response {
+    let ndjson = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',')
+                .any(|t| t.trim().starts_with("application/x-ndjson"))
+        });
+    if query.stream || ndjson {
+        if query.counts || query.group_by.is_some() {
+            let error = "Streamed trees don't support counts or group_by";
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": error })),
+            )
+                .into_response();
+        }
+        return stream_tree(&state).await;
+    }
+    buffered_tree(&state, query).await.into_response()
+}
+
+/// `/tree` as NDJSON, written while the tree is walked, so large repositories
+/// never hold the whole serialized response.
+async fn stream_tree(state: &AppState) -> Response {
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let Some(adi) = adi else {
+        return indexing::not_initialized(state).into_response();
+    };
+    let tree = match blocking::query(&adi, |adi| adi.get_tree()).await {
+        Ok(tree) => tree,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response();
+        }
+    };
+
+    let (lines, receiver) = tokio::sync::mpsc::channel(TREE_STREAM_BUFFER);
+    tokio::task::spawn_blocking(move || {
+        tree::walk(&tree, |node| {
+            let mut line = serde_json::to_vec(&node).unwrap();
+            line.push(b'\n');
+            // Fails once the client has gone, which ends the walk
+            lines
+                .blocking_send(Ok::<_, std::convert::Infallible>(line))
+                .is_ok()
+        });
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(receiver)),
+    )
+        .into_response()
+}
+
+async fn buffered_tree(
+    state: &AppState,
+    query: TreeQuery,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let by_package = match query.group_by.as_deref() {
+        None => false,
+        Some("crate" | "package") => true,
+        Some(other) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": format!("Unknown group_by: {}", other) })),
+            );
+        }
+    };
+
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
 
     match adi.as_ref() {
-        Some(adi) => match adi.get_file(std::path::Path::new(&path)) {
-            Ok(file_info) => (
-                StatusCode::OK,
-                Json(serde_json::to_value(file_info).unwrap()),
-            ),
+        Some(adi) => match blocking::query(adi, |adi| adi.get_tree()).await {
+            Ok(tree) => {
+                let directories = query.counts.then(|| tree::directory_counts(&tree));
+                let mut body = serde_json::to_value(&tree).unwrap();
+                body["root"] = tree::directory_id(tree::ROOT).into();
+                for (node, file) in body["files"]
+                    .as_array_mut()
+                    .into_iter()
+                    .flatten()
+                    .zip(&tree.files)
+                {
+                    node["id"] = tree::file_id(&file.path).into();
+                    node["parent"] = tree::parent_dir(&file.path).into();
+                }
+                if by_package {
+                    let project_path = state.project_path();
+                    let packages = tokio::task::spawn_blocking(move || {
+                        let dirs = tree::directory_counts(&tree)
+                            .into_keys()
+                            .collect::<Vec<_>>();
+                        let packages =
+                            packages::detect(&project_path, dirs.iter().map(String::as_str));
+                        (tree, packages)
+                    })
+                    .await;
+                    let Ok((tree, packages)) = packages else {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(serde_json::json!({ "error": "Package detection failed" })),
+                        );
+                    };
+
+                    let files = body["files"].take();
+                    let groups = tree::group_files(&tree, files, &packages);
+                    let object = body.as_object_mut().unwrap();
+                    object.remove("files");
+                    object.insert("group_by".into(), groups.kind.into());
+                    object.insert(
+                        "groups".into(),
+                        serde_json::to_value(groups.groups).unwrap(),
+                    );
+                }
+                if let Some(directories) = directories {
+                    body["directories"] = serde_json::to_value(directories).unwrap();
+                }
+                format::canonicalize_kinds(&mut body);
+                (StatusCode::OK, Json(body))
+            }
             Err(e) => (
-                StatusCode::NOT_FOUND,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({ "error": e.to_string() })),
             ),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "ADI not initialized" })),
-        ),
+        None => indexing::not_initialized(state),
     }
 }
 
-async fn get_tree(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let adi = state.adi.read().await;
+async fn get_tree_children(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
 
     match adi.as_ref() {
-        Some(adi) => match adi.get_tree() {
-            Ok(tree) => (StatusCode::OK, Json(serde_json::to_value(tree).unwrap())),
+        Some(adi) => match blocking::query(adi, |adi| adi.get_tree()).await {
+            Ok(tree) => match tree::children(&tree, &id) {
+                Some(children) => {
+                    let mut body = serde_json::to_value(children).unwrap();
+                    format::canonicalize_kinds(&mut body);
+                    (StatusCode::OK, Json(body))
+                }
+                None => (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({ "error": format!("Node not found: {}", id) })),
+                ),
+            },
             Err(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({ "error": e.to_string() })),
             ),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "ADI not initialized" })),
-        ),
+        None => indexing::not_initialized(&state),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use axum::extract::RawQuery;
+    use tower::ServiceExt;
+
+    async fn get_path(path: &str) -> (StatusCode, String) {
+        let routes = Router::new().route(
+            "/symbols",
+            get(|RawQuery(query): RawQuery| async move { query.unwrap_or_default() }),
+        );
+        let request = axum::http::Request::get(path).body(Body::empty()).unwrap();
+        let response = trim_trailing_slashes(routes)
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn paths_route_with_or_without_a_trailing_slash() {
+        assert_eq!(get_path("/symbols").await.0, StatusCode::OK);
+        assert_eq!(get_path("/symbols/").await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn trimming_keeps_the_query() {
+        assert_eq!(
+            get_path("/symbols/?q=Config").await,
+            (StatusCode::OK, "q=Config".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_paths_stay_unknown() {
+        assert_eq!(get_path("/nothing/").await.0, StatusCode::NOT_FOUND);
     }
 }
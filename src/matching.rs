@@ -0,0 +1,216 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Name matching over the index's symbol tree, for when callers want
+//! predictable semantics instead of adi_core's own ranking.
+
+use crate::catalog;
+use crate::suggest::levenshtein;
+use adi_core::{Adi, FileNode, Symbol, SymbolNode, Tree};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Lowest [`similarity`] reported by `/symbols/:id/similar`.
+pub const MIN_SIMILARITY: f32 = 0.3;
+
+#[derive(Serialize)]
+pub struct ScoredSymbol {
+    #[serde(flatten)]
+    pub symbol: Symbol,
+    pub score: f32,
+}
+
+/// Score in `0.0..=1.0` for `name` against a lowercased `query`, or `None`
+/// when it doesn't match. Substrings always match; other names only within
+/// `fuzzy_distance` edits.
+pub fn name_score(query: &str, name: &str, fuzzy_distance: Option<usize>) -> Option<f32> {
+    let name = name.to_lowercase();
+    let name_len = name.chars().count().max(1) as f32;
+
+    if name.contains(query) {
+        return Some(query.chars().count() as f32 / name_len);
+    }
+
+    let distance = levenshtein(query, &name);
+    (distance <= fuzzy_distance?).then(|| {
+        let longest = name_len.max(query.chars().count() as f32);
+        1.0 - distance as f32 / longest
+    })
+}
+
+/// For `case_sensitive=true`: false when some term of `query` occurs in
+/// `fields` only in a different case, e.g. `Config` against `config.rs`.
+/// Results not containing a term at all (semantic or fuzzy hits) pass.
+pub fn respects_case(query: &str, fields: &[&str]) -> bool {
+    query.split_whitespace().all(|term| {
+        let folded = term.to_lowercase();
+        let matched = fields.iter().any(|f| f.to_lowercase().contains(&folded));
+        !matched || fields.iter().any(|f| f.contains(term))
+    })
+}
+
+/// How `/symbols?match=` compares a name with the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameMatch {
+    Contains,
+    Prefix,
+    Suffix,
+    Exact,
+}
+
+impl NameMatch {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "contains" => Some(Self::Contains),
+            "prefix" => Some(Self::Prefix),
+            "suffix" => Some(Self::Suffix),
+            "exact" => Some(Self::Exact),
+            _ => None,
+        }
+    }
+
+    /// Whether `name` matches `query` in this mode, ignoring case unless
+    /// `case_sensitive`.
+    pub fn matches(self, query: &str, name: &str, case_sensitive: bool) -> bool {
+        let (query, name) = if case_sensitive {
+            (query.to_string(), name.to_string())
+        } else {
+            (query.to_lowercase(), name.to_lowercase())
+        };
+        match self {
+            Self::Contains => name.contains(&query),
+            Self::Prefix => name.starts_with(&query),
+            Self::Suffix => name.ends_with(&query),
+            Self::Exact => name == query,
+        }
+    }
+}
+
+/// Best `limit` symbols by name score, restricted to those accepted by `include`.
+pub fn match_names(
+    adi: &Adi,
+    tree: &Tree,
+    query: &str,
+    fuzzy_distance: Option<usize>,
+    limit: usize,
+    include: impl Fn(&FileNode, &SymbolNode) -> bool,
+) -> Vec<ScoredSymbol> {
+    let query = query.to_lowercase();
+
+    let mut scored: Vec<_> = catalog::symbol_nodes(tree)
+        .into_iter()
+        .filter(|(file, node)| include(file, node))
+        .filter_map(|(_, node)| {
+            name_score(&query, &node.name, fuzzy_distance).map(|score| (score, node.id))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    scored
+        .into_iter()
+        .take(limit)
+        .filter_map(|(score, id)| {
+            adi.get_symbol(id)
+                .ok()
+                .map(|symbol| ScoredSymbol { symbol, score })
+        })
+        .collect()
+}
+
+/// Lowercased words of a symbol's name and signature, splitting
+/// `snake_case` and `camelCase`, so `parseConfig(path: &Path)` yields
+/// `parse`, `config` and `path`.
+pub fn tokens(symbol: &Symbol) -> HashSet<String> {
+    let text = format!(
+        "{} {}",
+        symbol.name,
+        symbol.signature.as_deref().unwrap_or("")
+    );
+    let mut tokens = HashSet::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let mut current = String::new();
+        let mut previous_lower = false;
+        for c in word.chars() {
+            if c.is_uppercase() && previous_lower {
+                tokens.insert(std::mem::take(&mut current));
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+            current.extend(c.to_lowercase());
+        }
+        tokens.insert(current);
+    }
+    tokens.retain(|t| t.chars().count() > 1);
+    tokens
+}
+
+/// Token overlap (Jaccard) in `0.0..=1.0`, nudged up for symbols of the same
+/// kind so a function ranks other functions first.
+pub fn similarity(a: &Symbol, a_tokens: &HashSet<String>, b: &Symbol) -> f32 {
+    let b_tokens = tokens(b);
+    let union = a_tokens.union(&b_tokens).count();
+    if union == 0 {
+        return 0.0;
+    }
+    let overlap = a_tokens.intersection(&b_tokens).count() as f32 / union as f32;
+    if a.kind == b.kind {
+        (overlap * 1.1).min(1.0)
+    } else {
+        overlap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respects_case_keeps_exact_case_matches() {
+        assert!(respects_case("Config", &["Config", "src/config.rs"]));
+    }
+
+    #[test]
+    fn respects_case_drops_matches_only_in_another_case() {
+        assert!(!respects_case("Config", &["config", "src/config.rs"]));
+    }
+
+    #[test]
+    fn respects_case_needs_every_term() {
+        assert!(respects_case("Config load", &["Config", "src/load.rs"]));
+        assert!(!respects_case("Config Load", &["Config", "src/load.rs"]));
+    }
+
+    #[test]
+    fn respects_case_passes_results_without_the_term() {
+        assert!(respects_case("Config", &["Settings", "src/settings.rs"]));
+    }
+
+    #[test]
+    fn name_match_parses_the_four_modes_only() {
+        assert_eq!(NameMatch::parse("contains"), Some(NameMatch::Contains));
+        assert_eq!(NameMatch::parse("prefix"), Some(NameMatch::Prefix));
+        assert_eq!(NameMatch::parse("suffix"), Some(NameMatch::Suffix));
+        assert_eq!(NameMatch::parse("exact"), Some(NameMatch::Exact));
+        assert_eq!(NameMatch::parse("Exact"), None);
+        assert_eq!(NameMatch::parse("fuzzy"), None);
+    }
+
+    #[test]
+    fn name_match_modes_anchor_where_they_say() {
+        let name = "ConfigLoader";
+        assert!(NameMatch::Contains.matches("gload", name, false));
+        assert!(NameMatch::Prefix.matches("config", name, false));
+        assert!(!NameMatch::Prefix.matches("loader", name, false));
+        assert!(NameMatch::Suffix.matches("loader", name, false));
+        assert!(!NameMatch::Suffix.matches("config", name, false));
+        assert!(NameMatch::Exact.matches("configloader", name, false));
+        assert!(!NameMatch::Exact.matches("config", name, false));
+    }
+
+    #[test]
+    fn name_match_compares_case_only_when_asked() {
+        assert!(NameMatch::Exact.matches("config", "Config", false));
+        assert!(!NameMatch::Exact.matches("config", "Config", true));
+        assert!(NameMatch::Prefix.matches("Con", "Config", true));
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Package boundaries (Cargo crates, npm packages, ...) found from manifests.
+
+use serde::Serialize;
+use std::path::Path;
+
+/// Reads the package name out of a manifest's contents.
+type NameParser = fn(&str) -> Option<String>;
+
+/// Manifest file names that mark a package root, with their name parser.
+const MANIFESTS: &[(&str, NameParser)] = &[
+    ("Cargo.toml", |c| toml_name(c, "[package]")),
+    ("pyproject.toml", |c| toml_name(c, "[project]")),
+    ("package.json", json_name),
+    ("go.mod", go_module),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Package {
+    pub name: String,
+    /// Project-relative directory the package owns (`.` for the root)
+    pub path: String,
+    /// Project-relative path of the manifest that declared it
+    pub manifest: String,
+}
+
+/// Packages declared in any of `dirs` (project-relative). Reads manifests
+/// from disk, so it blocks.
+pub fn detect<'a>(project_path: &Path, dirs: impl IntoIterator<Item = &'a str>) -> Vec<Package> {
+    dirs.into_iter()
+        .filter_map(|dir| {
+            MANIFESTS.iter().find_map(|(file, parse)| {
+                let manifest = Path::new(dir).join(file);
+                let content = std::fs::read_to_string(project_path.join(&manifest)).ok()?;
+                Some(Package {
+                    name: parse(&content)?,
+                    path: dir.to_string(),
+                    manifest: manifest
+                        .strip_prefix(crate::tree::ROOT)
+                        .unwrap_or(&manifest)
+                        .to_string_lossy()
+                        .into_owned(),
+                })
+            })
+        })
+        .collect()
+}
+
+/// The innermost package containing the project-relative `file`.
+pub fn owner<'a>(packages: &'a [Package], file: &Path) -> Option<&'a Package> {
+    packages
+        .iter()
+        .filter(|p| p.path == crate::tree::ROOT || file.starts_with(&p.path))
+        .max_by_key(|p| p.path.len())
+}
+
+/// `name` under `section`. A virtual workspace root has no `[package]`, so
+/// it isn't a package of its own.
+fn toml_name(content: &str, section: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == section;
+        } else if in_section {
+            if let Some(value) = line.strip_prefix("name").map(str::trim_start) {
+                if let Some(value) = value.strip_prefix('=') {
+                    return Some(value.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+fn json_name(content: &str) -> Option<String> {
+    let manifest: serde_json::Value = serde_json::from_str(content).ok()?;
+    manifest["name"].as_str().map(str::to_owned)
+}
+
+fn go_module(content: &str) -> Option<String> {
+    content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("module "))
+        .map(|module| module.trim().to_string())
+}
@@ -0,0 +1,194 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! `offset` paging for list endpoints whose backends only take a `limit`.
+
+use axum::{
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Most results fetched to serve one page; offsets beyond come back empty.
+pub const MAX_WINDOW: usize = 10_000;
+
+#[derive(Clone, Copy)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Where a page sits in the full result list.
+#[derive(Serialize)]
+pub struct Paging {
+    pub offset: usize,
+    pub has_more: bool,
+    /// Known once the results ran out within the fetched window
+    pub total: Option<usize>,
+}
+
+impl Page {
+    /// Results to ask the backend for: one past the page, to tell whether
+    /// more follow, and never more than [`MAX_WINDOW`].
+    pub fn fetch(&self) -> usize {
+        self.offset
+            .saturating_add(self.limit)
+            .saturating_add(1)
+            .min(MAX_WINDOW)
+    }
+
+    /// Whether the page starts past [`MAX_WINDOW`], where it's always empty
+    /// and nothing needs fetching.
+    pub fn beyond_window(&self) -> bool {
+        self.offset >= MAX_WINDOW
+    }
+
+    /// Cuts the page out of `fetched`, which holds up to [`Page::fetch`] results.
+    pub fn slice<T>(&self, fetched: Vec<T>) -> (Vec<T>, Paging) {
+        let exhausted = fetched.len() < self.fetch();
+        self.slice_filtered(fetched, exhausted)
+    }
+
+    /// Like [`Page::slice`] for results filtered after fetching, where a short
+    /// list no longer means the backend ran out; `exhausted` says whether it
+    /// did, and the total is only reported then.
+    pub fn slice_filtered<T>(&self, fetched: Vec<T>, exhausted: bool) -> (Vec<T>, Paging) {
+        let found = fetched.len();
+        let paging = Paging {
+            offset: self.offset,
+            has_more: found > self.offset.saturating_add(self.limit),
+            total: exhausted.then_some(found),
+        };
+        let page = fetched
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect();
+        (page, paging)
+    }
+}
+
+/// Next, larger number of results to ask a backend for when filtering
+/// after the fetch left too few; `None` once [`MAX_WINDOW`] was asked for.
+pub fn widen(asked: usize) -> Option<usize> {
+    (asked < MAX_WINDOW).then(|| asked.saturating_mul(4).clamp(1, MAX_WINDOW))
+}
+
+/// Like [`widen`] for a search that starts below `limit`: rounds grow up to
+/// `limit` first, and only past it once filtering leaves too few.
+pub fn widen_toward(asked: usize, limit: usize) -> Option<usize> {
+    widen(asked).map(|more| if asked < limit { more.min(limit) } else { more })
+}
+
+/// `results` with `X-Total-Count` when the total is known, wrapped as
+/// `{results, offset, has_more, total}` under `verbose`.
+pub fn respond(results: impl Serialize, paging: &Paging, verbose: bool) -> Response {
+    if verbose {
+        return respond_with(results, paging, Map::new());
+    }
+    with_total(serde_json::to_value(results).unwrap(), paging)
+}
+
+/// Like [`respond`] under `verbose`, with `extra` fields alongside the page.
+pub fn respond_with(
+    results: impl Serialize,
+    paging: &Paging,
+    extra: Map<String, Value>,
+) -> Response {
+    let mut body = serde_json::json!({
+        "results": results,
+        "offset": paging.offset,
+        "has_more": paging.has_more,
+        "total": paging.total,
+    });
+    body.as_object_mut().unwrap().extend(extra);
+    with_total(body, paging)
+}
+
+fn with_total(body: Value, paging: &Paging) -> Response {
+    let mut response = (StatusCode::OK, Json(body)).into_response();
+    if let Some(total) = paging.total {
+        response
+            .headers_mut()
+            .insert("x-total-count", HeaderValue::from(total));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(offset: usize, limit: usize) -> Page {
+        Page { offset, limit }
+    }
+
+    #[test]
+    fn fetch_asks_for_one_past_the_page() {
+        assert_eq!(page(0, 20).fetch(), 21);
+        assert_eq!(page(40, 20).fetch(), 61);
+    }
+
+    #[test]
+    fn fetch_is_capped_at_the_window() {
+        assert_eq!(page(MAX_WINDOW - 5, 20).fetch(), MAX_WINDOW);
+        assert_eq!(page(usize::MAX, usize::MAX).fetch(), MAX_WINDOW);
+    }
+
+    #[test]
+    fn beyond_window_starts_at_the_cap() {
+        assert!(!page(MAX_WINDOW - 1, 20).beyond_window());
+        assert!(page(MAX_WINDOW, 20).beyond_window());
+    }
+
+    #[test]
+    fn slice_with_more_results_leaves_the_total_open() {
+        let (results, paging) = page(10, 10).slice((0..21).collect::<Vec<_>>());
+        assert_eq!(results, (10..20).collect::<Vec<_>>());
+        assert!(paging.has_more);
+        assert_eq!(paging.total, None);
+    }
+
+    #[test]
+    fn slice_of_a_short_fetch_knows_the_total() {
+        let (results, paging) = page(10, 10).slice((0..15).collect::<Vec<_>>());
+        assert_eq!(results, (10..15).collect::<Vec<_>>());
+        assert!(!paging.has_more);
+        assert_eq!(paging.total, Some(15));
+    }
+
+    #[test]
+    fn slice_past_the_results_is_empty() {
+        let (results, paging) = page(30, 10).slice((0..15).collect::<Vec<_>>());
+        assert!(results.is_empty());
+        assert!(!paging.has_more);
+        assert_eq!(paging.total, Some(15));
+    }
+
+    #[test]
+    fn slice_filtered_only_reports_a_total_once_exhausted() {
+        let (_, open) = page(0, 10).slice_filtered((0..5).collect::<Vec<_>>(), false);
+        assert_eq!(open.total, None);
+        let (_, known) = page(0, 10).slice_filtered((0..5).collect::<Vec<_>>(), true);
+        assert_eq!(known.total, Some(5));
+    }
+
+    #[test]
+    fn widen_grows_up_to_the_window() {
+        assert_eq!(widen(0), Some(1));
+        assert_eq!(widen(21), Some(84));
+        assert_eq!(widen(MAX_WINDOW - 1), Some(MAX_WINDOW));
+        assert_eq!(widen(MAX_WINDOW), None);
+    }
+
+    #[test]
+    fn widen_toward_stops_at_the_limit_first() {
+        assert_eq!(widen_toward(10, 25), Some(25));
+        assert_eq!(widen_toward(10, 100), Some(40));
+        assert_eq!(widen_toward(25, 25), Some(100));
+        assert_eq!(widen_toward(MAX_WINDOW, MAX_WINDOW), None);
+    }
+}
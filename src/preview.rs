@@ -0,0 +1,226 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Dry-run of indexing: which files would be parsed and why others wouldn't.
+//!
+//! adi_core doesn't expose the filters its walk applies, so the size limit,
+//! extensions and binary check here are this server's estimate of them. A
+//! dry run can count files differently from what `Adi::index` then does.
+
+use crate::indexing;
+use crate::stats::INDEX_DIR_NAME;
+use ignore::WalkBuilder;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Files larger than this are reported as too big to index. A guess;
+/// adi_core's own limit isn't exposed.
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Paths listed per outcome; the counts cover everything.
+const SAMPLE_SIZE: usize = 20;
+
+/// Extensions of the languages adi_core is expected to parse.
+const PARSED_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "mjs", "cjs", "jsx", "ts", "tsx", "go", "java", "c", "h", "cc", "cpp", "cxx",
+    "hpp", "cs", "rb", "php",
+];
+
+/// Bytes inspected when deciding whether a file is binary.
+const BINARY_PROBE_BYTES: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    Ignored,
+    TooBig,
+    Binary,
+    UnsupportedLanguage,
+}
+
+#[derive(Default, Serialize)]
+pub struct SkipCounts {
+    pub ignored: usize,
+    pub too_big: usize,
+    pub binary: usize,
+    pub unsupported_language: usize,
+}
+
+#[derive(Serialize)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+#[derive(Default, Serialize)]
+pub struct Preview {
+    pub included: usize,
+    pub skipped: SkipCounts,
+    pub sample_included: Vec<PathBuf>,
+    pub sample_skipped: Vec<Skipped>,
+}
+
+impl Preview {
+    fn include(&mut self, path: PathBuf) {
+        self.included += 1;
+        if self.sample_included.len() < SAMPLE_SIZE {
+            self.sample_included.push(path);
+        }
+    }
+
+    fn skip(&mut self, path: PathBuf, reason: SkipReason) {
+        let count = match reason {
+            SkipReason::Ignored => &mut self.skipped.ignored,
+            SkipReason::TooBig => &mut self.skipped.too_big,
+            SkipReason::Binary => &mut self.skipped.binary,
+            SkipReason::UnsupportedLanguage => &mut self.skipped.unsupported_language,
+        };
+        *count += 1;
+        if self.sample_skipped.len() < SAMPLE_SIZE {
+            self.sample_skipped.push(Skipped { path, reason });
+        }
+    }
+}
+
+/// Walks `project_path` without parsing anything. Blocking.
+pub fn preview(project_path: &Path) -> Preview {
+    // Everything the ignore rules let through; the rest of the tree is "ignored"
+    let kept: HashSet<PathBuf> = files(WalkBuilder::new(project_path).hidden(false))
+        .into_iter()
+        .collect();
+    let everything = files(WalkBuilder::new(project_path).standard_filters(false));
+
+    let mut preview = Preview::default();
+    for path in everything {
+        let relative = path
+            .strip_prefix(project_path)
+            .unwrap_or(&path)
+            .to_path_buf();
+
+        match skip_reason(&path, kept.contains(&path)) {
+            Some(reason) => preview.skip(relative, reason),
+            None => preview.include(relative),
+        }
+    }
+    preview
+}
+
+fn files(builder: &mut WalkBuilder) -> Vec<PathBuf> {
+    builder
+        .filter_entry(|entry| {
+            let name = entry.file_name();
+            name != ".git" && name != INDEX_DIR_NAME && name != indexing::BACKUP_DIR_NAME
+        })
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+fn skip_reason(path: &Path, kept: bool) -> Option<SkipReason> {
+    if !kept {
+        return Some(SkipReason::Ignored);
+    }
+    if !has_parser(path) {
+        return Some(SkipReason::UnsupportedLanguage);
+    }
+    if std::fs::metadata(path).is_ok_and(|m| m.len() > MAX_FILE_SIZE) {
+        return Some(SkipReason::TooBig);
+    }
+    if is_binary(path) {
+        return Some(SkipReason::Binary);
+    }
+    None
+}
+
+fn has_parser(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    PARSED_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+}
+
+fn is_binary(path: &Path) -> bool {
+    let mut probe = Vec::with_capacity(BINARY_PROBE_BYTES);
+    match std::fs::File::open(path) {
+        Ok(file) => {
+            let _ = file.take(BINARY_PROBE_BYTES as u64).read_to_end(&mut probe);
+            probe.contains(&0)
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, content: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn includes_source_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path(), "lib.rs", b"fn main() {}");
+        assert_eq!(skip_reason(&path, true), None);
+    }
+
+    #[test]
+    fn reports_each_skip_reason() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = write(dir.path(), "lib.rs", b"fn main() {}");
+        assert_eq!(skip_reason(&source, false), Some(SkipReason::Ignored));
+
+        let notes = write(dir.path(), "notes.md", b"# Notes");
+        assert_eq!(
+            skip_reason(&notes, true),
+            Some(SkipReason::UnsupportedLanguage)
+        );
+
+        let big = write(dir.path(), "big.rs", b"");
+        std::fs::File::options()
+            .write(true)
+            .open(&big)
+            .unwrap()
+            .set_len(MAX_FILE_SIZE + 1)
+            .unwrap();
+        assert_eq!(skip_reason(&big, true), Some(SkipReason::TooBig));
+
+        let binary = write(dir.path(), "blob.c", b"\x7fELF\0\0");
+        assert_eq!(skip_reason(&binary, true), Some(SkipReason::Binary));
+    }
+
+    #[test]
+    fn extensions_match_ignoring_case() {
+        assert!(has_parser(Path::new("Main.RS")));
+        assert!(!has_parser(Path::new("Makefile")));
+    }
+
+    #[test]
+    fn preview_counts_ignored_files_and_skips_index_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), ".ignore", b"generated.rs\n");
+        write(dir.path(), "src/lib.rs", b"fn a() {}");
+        write(dir.path(), "generated.rs", b"fn b() {}");
+        write(dir.path(), "README.md", b"# Readme");
+        write(dir.path(), ".adi/index.db", b"\0");
+        write(dir.path(), ".adi.bak/index.db", b"\0");
+
+        let preview = preview(dir.path());
+        assert_eq!(preview.included, 1);
+        assert_eq!(preview.sample_included, [PathBuf::from("src/lib.rs")]);
+        assert_eq!(preview.skipped.ignored, 1);
+        // `.ignore` and README.md
+        assert_eq!(preview.skipped.unsupported_language, 2);
+        assert_eq!(preview.skipped.too_big + preview.skipped.binary, 0);
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Re-ranking `/search` results by where the query matched.
+
+use adi_core::SearchResult;
+use serde::Serialize;
+
+/// Largest weight accepted for any field.
+pub const MAX_WEIGHT: f32 = 10.0;
+
+/// Bonus per field, scaled by the share of query terms found in it and added
+/// to adi_core's score. All zero keeps adi_core's ranking untouched.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Weights {
+    pub name: f32,
+    pub path: f32,
+    pub doc: f32,
+}
+
+impl Weights {
+    pub fn is_neutral(&self) -> bool {
+        self.name == 0.0 && self.path == 0.0 && self.doc == 0.0
+    }
+}
+
+/// A score split into what adi_core reported and what each weighted field
+/// added; the parts sum to the result's `score`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Explanation {
+    pub relevance: f32,
+    pub name: f32,
+    pub path: f32,
+    pub doc: f32,
+}
+
+impl Explanation {
+    /// Breaks down a result already passed through [`rerank`].
+    pub fn of(result: &SearchResult, query: &str, weights: &Weights) -> Self {
+        let terms = terms(query);
+        let [name, path, doc] = bonuses(&result.symbol, &terms, weights);
+        Self {
+            relevance: result.score - (name + path + doc),
+            name,
+            path,
+            doc,
+        }
+    }
+}
+
+/// Whether `weight` is within `0.0..=MAX_WEIGHT`.
+pub fn valid_weight(weight: f32) -> bool {
+    (0.0..=MAX_WEIGHT).contains(&weight)
+}
+
+/// Applies `weights` to every score and re-sorts, best first.
+pub fn rerank(results: &mut [SearchResult], query: &str, weights: &Weights) {
+    if weights.is_neutral() {
+        return;
+    }
+
+    let terms = terms(query);
+    for result in results.iter_mut() {
+        result.score += field_bonus(&result.symbol, &terms, weights);
+    }
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+}
+
+fn terms(query: &str) -> Vec<String> {
+    query.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// What `weights` add to a symbol's score for `terms` (lowercased).
+pub fn field_bonus(symbol: &adi_core::Symbol, terms: &[String], weights: &Weights) -> f32 {
+    bonuses(symbol, terms, weights).iter().sum()
+}
+
+/// The name, path and doc parts of [`field_bonus`].
+fn bonuses(symbol: &adi_core::Symbol, terms: &[String], weights: &Weights) -> [f32; 3] {
+    let doc = [symbol.doc_comment.as_deref(), symbol.description.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    [
+        weights.name * coverage(&symbol.name, terms),
+        weights.path * coverage(&symbol.file_path.to_string_lossy(), terms),
+        weights.doc * coverage(&doc, terms),
+    ]
+}
+
+/// Share of `terms` occurring in `field`, case-insensitively.
+fn coverage(field: &str, terms: &[String]) -> f32 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let field = field.to_lowercase();
+    let found = terms.iter().filter(|t| field.contains(t.as_str())).count();
+    found as f32 / terms.len() as f32
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Version markers stored beside adi_core's index, so an index written by an
+//! incompatible release, of this server or of adi_core, is reported instead
+//! of misread.
+
+use crate::stats::INDEX_DIR_NAME;
+use serde::Serialize;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Bumped whenever indexes written by earlier releases stop being usable.
+pub const INDEX_SCHEMA_VERSION: u32 = 1;
+
+/// adi_core release this build indexes with, from Cargo.lock via build.rs.
+/// The index files are adi_core's, so a different release may not read them.
+pub const ADI_CORE_VERSION: &str = env!("ADI_CORE_VERSION");
+
+/// File inside the index directory holding the version that wrote it.
+const MARKER_FILE: &str = "schema_version";
+
+/// File inside the index directory holding the adi_core release that wrote it.
+const ADI_CORE_MARKER_FILE: &str = "adi_core_version";
+
+#[derive(Serialize)]
+pub struct SchemaCheck {
+    pub expected: u32,
+    /// `null` for indexes written before the marker existed
+    pub found: Option<u32>,
+    /// adi_core release this build indexes with
+    pub adi_core: &'static str,
+    /// adi_core release that wrote the index; `null` for indexes written
+    /// before it was recorded
+    pub written_by: Option<String>,
+    pub compatible: bool,
+}
+
+/// Records [`INDEX_SCHEMA_VERSION`] and [`ADI_CORE_VERSION`] in the index
+/// under `project_path`.
+pub async fn record(project_path: &Path) {
+    let index_dir = project_path.join(INDEX_DIR_NAME);
+    let markers = [
+        (MARKER_FILE, INDEX_SCHEMA_VERSION.to_string()),
+        (ADI_CORE_MARKER_FILE, ADI_CORE_VERSION.to_string()),
+    ];
+    for (file, version) in markers {
+        let marker = index_dir.join(file);
+        if let Err(e) = tokio::fs::write(&marker, version).await {
+            tracing::warn!("Failed to write {}: {}", marker.display(), e);
+        }
+    }
+}
+
+/// When the index under `project_path` was last recorded, by the marker's
+/// mtime; `None` for indexes written before the marker existed.
+pub fn recorded_at(project_path: &Path) -> Option<SystemTime> {
+    let marker = project_path.join(INDEX_DIR_NAME).join(MARKER_FILE);
+    std::fs::metadata(marker).ok()?.modified().ok()
+}
+
+/// Compares the index's recorded versions with this build's: both the
+/// server's schema and the adi_core release that wrote the index must
+/// match. Versions that weren't recorded are assumed compatible.
+pub async fn check(project_path: &Path) -> SchemaCheck {
+    let index_dir = project_path.join(INDEX_DIR_NAME);
+    let read = |file: &str| {
+        let marker = index_dir.join(file);
+        async move {
+            tokio::fs::read_to_string(&marker)
+                .await
+                .ok()
+                .map(|version| version.trim().to_string())
+        }
+    };
+    let found = read(MARKER_FILE)
+        .await
+        .and_then(|version| version.parse().ok());
+    let written_by = read(ADI_CORE_MARKER_FILE).await;
+
+    SchemaCheck {
+        expected: INDEX_SCHEMA_VERSION,
+        found,
+        adi_core: ADI_CORE_VERSION,
+        compatible: compatible(found, written_by.as_deref()),
+        written_by,
+    }
+}
+
+fn compatible(found: Option<u32>, written_by: Option<&str>) -> bool {
+    found.is_none_or(|found| found == INDEX_SCHEMA_VERSION)
+        && written_by.is_none_or(|release| release == ADI_CORE_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unmarked_indexes_are_compatible() {
+        assert!(compatible(None, None));
+    }
+
+    #[test]
+    fn another_schema_is_incompatible() {
+        assert!(compatible(Some(INDEX_SCHEMA_VERSION), None));
+        assert!(!compatible(Some(INDEX_SCHEMA_VERSION + 1), None));
+    }
+
+    #[test]
+    fn another_adi_core_release_is_incompatible() {
+        assert!(compatible(
+            Some(INDEX_SCHEMA_VERSION),
+            Some(ADI_CORE_VERSION)
+        ));
+        assert!(!compatible(Some(INDEX_SCHEMA_VERSION), Some("0.0.0-other")));
+    }
+
+    #[tokio::test]
+    async fn check_reads_what_record_wrote() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(INDEX_DIR_NAME)).unwrap();
+        record(dir.path()).await;
+
+        let check = check(dir.path()).await;
+        assert_eq!(check.found, Some(INDEX_SCHEMA_VERSION));
+        assert_eq!(check.written_by.as_deref(), Some(ADI_CORE_VERSION));
+        assert!(check.compatible);
+    }
+}
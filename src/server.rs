@@ -0,0 +1,317 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::config::Config;
+use crate::AppState;
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+
+/// Accept loop that holds at most `MAX_CONNECTIONS` connections open;
+/// further clients wait in the listen backlog. Connections speak HTTP/1.1,
+/// or HTTP/2 with prior knowledge (h2c) unless `H2C` is off.
+pub async fn serve(listener: TcpListener, app: Router, config: &Config) {
+    let connections = Arc::new(Semaphore::new(config.max_connections));
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(config.keepalive.is_some())
+        // Also runs while a kept-alive connection waits for its next request,
+        // which makes it the HTTP/1.1 idle timeout
+        .header_read_timeout(http1_idle_timeout(config));
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .keep_alive_interval(config.keepalive);
+    if !config.h2c {
+        builder = builder.http1_only();
+    }
+
+    loop {
+        let permit = connections
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("connection semaphore is never closed");
+
+        let (stream, remote) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                // Usually fd exhaustion; back off instead of spinning
+                tracing::warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let service = TowerToHyperService::new(app.clone());
+        let builder = builder.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let io = TokioIo::new(stream);
+
+            if let Err(e) = builder.serve_connection_with_upgrades(io, service).await {
+                tracing::debug!("Connection from {} ended: {}", remote, e);
+            }
+        });
+    }
+}
+
+/// How long an HTTP/1.1 connection may sit between requests: the shorter
+/// of `KEEPALIVE_SECS` and `HEADER_READ_TIMEOUT_SECS`, so neither setting
+/// lets an idle connection outlive the other.
+fn http1_idle_timeout(config: &Config) -> Option<Duration> {
+    match (config.keepalive, config.header_read_timeout) {
+        (Some(keepalive), Some(header_read)) => Some(keepalive.min(header_read)),
+        (keepalive, header_read) => keepalive.or(header_read),
+    }
+}
+
+/// Honors a caller's `X-Request-Deadline-Ms` budget, clamped to
+/// `MAX_REQUEST_DEADLINE_MS`: a request still unanswered by then gets `408`
+/// and its work is dropped, which also cancels blocking index scans.
+pub async fn deadline(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let requested = request
+        .headers()
+        .get("x-request-deadline-ms")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let Some(ms) = requested else {
+        return next.run(request).await;
+    };
+
+    let budget = Duration::from_millis(ms).min(state.config.max_request_deadline);
+    match tokio::time::timeout(budget, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => (
+            StatusCode::REQUEST_TIMEOUT,
+            Json(serde_json::json!({
+                "error": format!("Request deadline of {} ms exceeded", budget.as_millis())
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Query parameters forwarded to adi_core as search text.
+const SEARCH_PARAMS: [&str; 2] = ["q", "signature"];
+
+/// `400` for a `q` or `signature` longer than `MAX_QUERY_LEN` characters,
+/// before the request is admitted or takes any lock.
+pub async fn query_len(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let params = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .map(|Query(params)| params)
+        .unwrap_or_default();
+    for name in SEARCH_PARAMS {
+        if let Err(rejection) = check_query_len(&state, name, params.get(name)) {
+            return rejection.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// `400` when search text `value` of parameter `name` is over `MAX_QUERY_LEN`.
+pub fn check_query_len(
+    state: &AppState,
+    name: &str,
+    value: Option<&String>,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let max = state.config.max_query_len;
+    match value.map(|v| v.chars().count()) {
+        Some(len) if len > max => Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("{} is {} characters long; at most {} are allowed", name, len, max)
+            })),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Largest `503` body [`retry_after`] reads; handlers' error bodies are
+/// far smaller.
+const MAX_BUSY_BODY_BYTES: usize = 64 * 1024;
+
+/// `503` for a request that waited `timeout` for the index lock without
+/// getting it; see [`AppState::read_adi`].
+pub fn index_busy(state: &AppState, timeout: Duration) -> (StatusCode, Json<serde_json::Value>) {
+    // A running index job is the likely holder, so its ETA is the best guess
+    let retry_after = state
+        .jobs
+        .current()
+        .and_then(|job| job.eta_ms)
+        .map_or(timeout, Duration::from_millis)
+        .max(Duration::from_secs(1));
+    tracing::warn!(
+        "Index lock not acquired within {} ms, answering 503",
+        timeout.as_millis()
+    );
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::json!({
+            "error": "index busy",
+            "retry_after_ms": retry_after.as_millis() as u64,
+        })),
+    )
+}
+
+/// Adds `Retry-After`, in whole seconds, to `503` responses whose JSON body
+/// gives `retry_after_ms`, as handlers answer with a status and body only.
+pub async fn retry_after(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if response.status() != StatusCode::SERVICE_UNAVAILABLE
+        || response.headers().contains_key(header::RETRY_AFTER)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_BUSY_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer 503 response: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to read response body" })),
+            )
+                .into_response();
+        }
+    };
+    let retry_after_ms = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|body| body.get("retry_after_ms")?.as_u64());
+    if let Some(ms) = retry_after_ms {
+        parts
+            .headers
+            .insert(header::RETRY_AFTER, HeaderValue::from(ms.div_ceil(1000)));
+    }
+    Response::from_parts(parts, axum::body::Body::from(bytes))
+}
+
+/// Stamps every response with `X-Index-Generation`, the number of index
+/// swaps since startup, and answers `412` when a request's
+/// `If-Index-Generation` no longer matches, before or after the handler
+/// runs, so multi-call clients notice ids from a replaced index.
+pub async fn index_generation(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let current = || state.generation.load(Ordering::Acquire);
+    // Reindex requests change the generation themselves
+    let expected = request
+        .headers()
+        .get("if-index-generation")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|_| !request.uri().path().starts_with("/admin"));
+
+    let before = current();
+    if expected.is_some_and(|expected| expected != before) {
+        return generation_changed(before);
+    }
+    let mut response = next.run(request).await;
+    // A swap mid-request may have served either index, so only the older
+    // generation is safe to report
+    let after = current();
+    if expected.is_some() && after != before {
+        return generation_changed(after);
+    }
+    response
+        .headers_mut()
+        .insert("x-index-generation", HeaderValue::from(before));
+    response
+}
+
+fn generation_changed(current: u64) -> Response {
+    (
+        StatusCode::PRECONDITION_FAILED,
+        [("x-index-generation", current)],
+        Json(serde_json::json!({
+            "error": "index changed since If-Index-Generation",
+            "generation": current,
+        })),
+    )
+        .into_response()
+}
+
+/// Admits at most `MAX_CONCURRENCY + MAX_QUEUE` requests; the rest get a
+/// `503` right away instead of piling up behind the concurrency limit.
+pub async fn admit(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let Ok(_admitted) = state.admission.try_acquire() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "1")],
+            Json(serde_json::json!({ "error": "Server busy, retry shortly" })),
+        )
+            .into_response();
+    };
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get};
+    use tower::ServiceExt;
+
+    async fn retry_header(status: StatusCode, body: serde_json::Value) -> Option<String> {
+        let app = Router::new()
+            .route(
+                "/",
+                get(move || {
+                    let body = body.clone();
+                    async move { (status, Json(body)) }
+                }),
+            )
+            .layer(middleware::from_fn(retry_after));
+        let request = axum::http::Request::get("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn busy_responses_get_retry_after_in_whole_seconds() {
+        let body = serde_json::json!({ "error": "index busy", "retry_after_ms": 1500 });
+        let header = retry_header(StatusCode::SERVICE_UNAVAILABLE, body).await;
+        assert_eq!(header.as_deref(), Some("2"));
+    }
+
+    #[tokio::test]
+    async fn other_responses_are_left_alone() {
+        let body = serde_json::json!({ "error": "ADI not initialized" });
+        assert_eq!(
+            retry_header(StatusCode::SERVICE_UNAVAILABLE, body).await,
+            None
+        );
+        let body = serde_json::json!({ "retry_after_ms": 1500 });
+        assert_eq!(retry_header(StatusCode::OK, body).await, None);
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Directory adi_core keeps its index in, relative to the project root.
+pub const INDEX_DIR_NAME: &str = ".adi";
+
+pub async fn stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let loaded = match state.read_adi().await {
+        Ok(adi) => adi.is_some(),
+        Err(rejection) => return rejection,
+    };
+    let index_dir = state.project_path().join(INDEX_DIR_NAME);
+
+    let dir = index_dir.clone();
+    let index_size_bytes = match tokio::task::spawn_blocking(move || dir_size(&dir)).await {
+        Ok(size) => size,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            );
+        }
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "loaded": loaded,
+            "index_dir": index_dir,
+            "index_size_bytes": index_size_bytes,
+            // The loaded Adi dominates the process, so RSS approximates its footprint
+            "resident_memory_bytes": resident_memory_bytes(),
+            "cached_entries": state.cache.len(),
+            "last_index": state.jobs.last_throughput(),
+        })),
+    )
+}
+
+/// Total size of regular files beneath `path`; unreadable entries count as zero.
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Resident set size of this process, where the platform exposes it.
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
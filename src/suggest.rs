@@ -0,0 +1,154 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::catalog;
+use std::collections::BTreeSet;
+
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Indexed symbol names close to `query`, nearest first.
+pub fn did_you_mean(adi: &adi_core::Adi, query: &str) -> Vec<String> {
+    let tree = match adi.get_tree() {
+        Ok(tree) => tree,
+        Err(e) => {
+            tracing::debug!("Skipping suggestions: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let names: BTreeSet<&str> = catalog::symbol_nodes(&tree)
+        .into_iter()
+        .map(|(_, symbol)| symbol.name.as_str())
+        .collect();
+
+    closest(query, names, MAX_SUGGESTIONS)
+}
+
+/// Up to `max` candidates within a query-length-relative edit distance,
+/// ordered by distance and then name.
+pub fn closest<'a>(
+    query: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    max: usize,
+) -> Vec<String> {
+    let query = query.to_lowercase();
+    let threshold = (query.chars().count() / 3).max(1);
+
+    let mut scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|name| {
+            let distance = levenshtein(&query, &name.to_lowercase());
+            (distance <= threshold).then_some((distance, name))
+        })
+        .collect();
+
+    scored.sort();
+    scored
+        .into_iter()
+        .take(max)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// The route in `routes` (axum syntax, `:param` and `*rest`) closest to
+/// `path`, filled in with the request's own segments, e.g. `/symbol/5`
+/// becomes `/symbols/5`.
+pub fn route(path: &str, routes: &[&str]) -> Option<String> {
+    let requested: Vec<&str> = segments(path).collect();
+    let mut best: Option<(usize, String)> = None;
+
+    for template in routes {
+        let template: Vec<&str> = segments(template).collect();
+        let rest = template.last().is_some_and(|s| s.starts_with('*'));
+        let fits = if rest {
+            requested.len() >= template.len()
+        } else {
+            requested.len() == template.len()
+        };
+        if !fits {
+            continue;
+        }
+
+        let mut distance = 0;
+        let mut filled = Vec::with_capacity(requested.len());
+        let matched = template.iter().zip(&requested).all(|(expected, actual)| {
+            if expected.starts_with([':', '*']) {
+                filled.push(*actual);
+                return true;
+            }
+            let d = levenshtein(&actual.to_lowercase(), expected);
+            distance += d;
+            filled.push(*expected);
+            d <= (expected.chars().count() / 3).max(1)
+        });
+        if !matched || best.as_ref().is_some_and(|(d, _)| *d <= distance) {
+            continue;
+        }
+
+        filled.extend(&requested[template.len()..]);
+        best = Some((distance, format!("/{}", filled.join("/"))));
+    }
+
+    best.filter(|(distance, _)| *distance > 0)
+        .map(|(_, route)| route)
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levenshtein_counts_single_edits() {
+        assert_eq!(levenshtein("config", "config"), 0);
+        assert_eq!(levenshtein("config", "confg"), 1);
+        assert_eq!(levenshtein("config", "configs"), 1);
+        assert_eq!(levenshtein("config", "conf1g"), 1);
+    }
+
+    #[test]
+    fn levenshtein_handles_empty_strings() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abc", ""), 3);
+    }
+
+    #[test]
+    fn levenshtein_counts_characters_not_bytes() {
+        assert_eq!(levenshtein("größe", "grösse"), 2);
+        assert_eq!(levenshtein("名前", "名"), 1);
+    }
+
+    #[test]
+    fn closest_orders_by_distance_then_name() {
+        let names = ["parse_config", "Config", "config", "configs", "unrelated"];
+        assert_eq!(closest("confg", names, 5), ["Config", "config"]);
+    }
+
+    #[test]
+    fn closest_stops_at_max() {
+        let names = ["config", "configs", "conf1g"];
+        assert_eq!(closest("config", names, 2).len(), 2);
+    }
+}
@@ -0,0 +1,760 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::matching::{self, ScoredSymbol};
+use crate::{blocking, catalog, git, indexing, AppState};
+use adi_core::SymbolNode;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Component, Path as FsPath, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Similar symbols returned without a `limit`.
+const DEFAULT_SIMILAR_LIMIT: usize = 10;
+
+/// File stems that name their directory's module rather than one of their own.
+const MODULE_ROOTS: [&str; 5] = ["lib", "main", "mod", "index", "__init__"];
+
+#[derive(Deserialize)]
+pub struct ScopeQuery {
+    /// Only count files beneath this project-relative directory
+    #[serde(default)]
+    scope: String,
+}
+
+pub async fn kinds(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ScopeQuery>,
+) -> impl IntoResponse {
+    let scope = catalog::normalize_scope(&query.scope);
+
+    // Counts from an index swapped out since are never used
+    let current = state.generation.load(Ordering::Acquire);
+    if let Some((_, counts)) = state
+        .cache
+        .kind_counts
+        .lock()
+        .unwrap()
+        .get(&scope)
+        .filter(|(counted, _)| *counted == current)
+    {
+        return (StatusCode::OK, Json(serde_json::to_value(counts).unwrap()));
+    }
+
+    let (adi, generation) = match state.read_adi_at().await {
+        Ok(loaded) => loaded,
+        Err(rejection) => return rejection,
+    };
+
+    match adi.as_ref() {
+        Some(adi) => match blocking::query(adi, |adi| adi.get_tree()).await {
+            Ok(tree) => {
+                let mut counts = BTreeMap::new();
+                for (_, symbol) in catalog::symbol_nodes(&tree)
+                    .into_iter()
+                    .filter(|(file, _)| catalog::in_scope(&file.path, &scope))
+                {
+                    let raw = catalog::kind_name(&symbol.kind);
+                    let kind = catalog::canonical_kind(&raw).map_or(raw, str::to_string);
+                    *counts.entry(kind).or_insert(0) += 1;
+                }
+
+                let body = serde_json::to_value(&counts).unwrap();
+                // Only scopes holding indexed symbols are kept, so the cache is
+                // bounded by the tree rather than by what clients send
+                if scope.is_empty() || !counts.is_empty() {
+                    state
+                        .cache
+                        .kind_counts
+                        .lock()
+                        .unwrap()
+                        .insert(scope, (generation, counts));
+                }
+                (StatusCode::OK, Json(body))
+            }
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            ),
+        },
+        None => indexing::not_initialized(&state),
+    }
+}
+
+/// How a symbol changed since the previous index; `null` without one.
+pub async fn diff(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+
+    match adi.as_ref() {
+        Some(adi) => {
+            match blocking::query(adi, move |adi| adi.get_symbol(adi_core::SymbolId(id))).await {
+                Ok(symbol) => {
+                    let previous = state.previous_index.lock().unwrap();
+                    let diff = previous.as_ref().map(|snapshot| snapshot.diff(&symbol));
+                    (StatusCode::OK, Json(serde_json::to_value(diff).unwrap()))
+                }
+                Err(e) => (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({ "error": e.to_string() })),
+                ),
+            }
+        }
+        None => indexing::not_initialized(&state),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BlameQuery {
+    /// Only the per-author counts, without `lines`
+    #[serde(default)]
+    summary: bool,
+}
+
+#[derive(Serialize)]
+struct Author {
+    author: String,
+    author_email: String,
+    /// Lines of the symbol this author changed last
+    lines: usize,
+    /// `lines` as a fraction of the symbol's, `0.0..=1.0`
+    share: f64,
+}
+
+/// Who last changed each line of symbol `id`, per `git blame` of the
+/// checked-out file, plus line counts per author, most first.
+pub async fn blame(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<BlameQuery>,
+) -> impl IntoResponse {
+    if !state.git_repo {
+        return git::not_a_repository("blame");
+    }
+
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi.as_ref() else {
+        return indexing::not_initialized(&state);
+    };
+
+    let symbol = match blocking::query(adi, move |adi| adi.get_symbol(adi_core::SymbolId(id))).await
+    {
+        Ok(symbol) => symbol,
+        Err(e) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            );
+        }
+    };
+    let location = &symbol.location;
+    let (start, end) = (
+        location.start_line,
+        location.end_line.max(location.start_line),
+    );
+    let lines = match git::blame(&state.project_path(), &symbol.file_path, start, end).await {
+        Ok(lines) => lines,
+        Err((status, e)) => {
+            return (
+                status,
+                Json(serde_json::json!({
+                    "error": format!("No blame for {}: {}", symbol.file_path.display(), e)
+                })),
+            );
+        }
+    };
+
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for line in &lines {
+        *counts
+            .entry((&line.author, &line.author_email))
+            .or_default() += 1;
+    }
+    let mut authors: Vec<Author> = counts
+        .into_iter()
+        .map(|((author, author_email), count)| Author {
+            author: author.to_string(),
+            author_email: author_email.to_string(),
+            lines: count,
+            share: count as f64 / lines.len() as f64,
+        })
+        .collect();
+    authors.sort_by(|a, b| b.lines.cmp(&a.lines));
+
+    let mut body = serde_json::json!({
+        "id": id,
+        "file_path": symbol.file_path,
+        "start_line": start,
+        "end_line": end,
+        "dominant_author": authors.first(),
+        "authors": authors,
+    });
+    if !query.summary {
+        body["lines"] = serde_json::to_value(&lines).unwrap();
+    }
+    (StatusCode::OK, Json(body))
+}
+
+#[derive(Deserialize)]
+pub struct ResolveQuery {
+    /// `::` or `.` separated, e.g. `crate::config::Config::from_env`
+    qualified: String,
+    /// Only symbols in this project-relative file
+    file: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Resolved {
+    id: adi_core::SymbolId,
+    name: String,
+    kind: adi_core::SymbolKind,
+    file_path: PathBuf,
+    qualified: String,
+}
+
+/// The symbol id a qualified name currently has, so links keyed by name
+/// survive reindexes that renumber symbols. `300` lists the candidates
+/// when the name is ambiguous.
+pub async fn resolve(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ResolveQuery>,
+) -> impl IntoResponse {
+    let wanted = segments(&query.qualified);
+    if wanted.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Missing query parameter: qualified" })),
+        );
+    }
+
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi.as_ref() else {
+        return indexing::not_initialized(&state);
+    };
+
+    let file = query.file.as_deref().map(catalog::normalize_scope);
+    let matches = blocking::query(adi, move |adi| {
+        let tree = adi.get_tree()?;
+        let mut matches = Vec::new();
+        for node in &tree.files {
+            if file.as_ref().is_some_and(|f| node.path != FsPath::new(f)) {
+                continue;
+            }
+            let mut found = Vec::new();
+            collect(&node.symbols, module_path(&node.path), &wanted, &mut found);
+            matches.extend(found.into_iter().map(|(qualified, symbol)| Resolved {
+                id: symbol.id,
+                name: symbol.name.clone(),
+                kind: symbol.kind,
+                file_path: node.path.clone(),
+                qualified,
+            }));
+        }
+        adi_core::Result::Ok(matches)
+    })
+    .await;
+
+    match matches {
+        Ok(matches) => match matches.len() {
+            0 => (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": format!("No symbol named {}", query.qualified)
+                })),
+            ),
+            1 => (
+                StatusCode::OK,
+                Json(serde_json::to_value(&matches[0]).unwrap()),
+            ),
+            _ => (
+                StatusCode::MULTIPLE_CHOICES,
+                Json(serde_json::json!({
+                    "error": format!("{} is ambiguous; pass file to choose", query.qualified),
+                    "candidates": matches,
+                })),
+            ),
+        },
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
+}
+
+fn segments(qualified: &str) -> Vec<String> {
+    qualified
+        .split("::")
+        .flat_map(|part| part.split('.'))
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Module segments a file contributes, e.g. `src/config/mod.rs` is
+/// `crate::config`. Qualified names are matched by suffix, so languages
+/// without a `crate` root still resolve by their trailing segments.
+fn module_path(path: &FsPath) -> Vec<String> {
+    let mut module = vec!["crate".to_string()];
+    let parts: Vec<&str> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        let name = if last {
+            FsPath::new(part)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or(part)
+        } else {
+            part
+        };
+        if (i == 0 && name == "src") || (last && MODULE_ROOTS.contains(&name)) {
+            continue;
+        }
+        module.push(name.to_string());
+    }
+    module
+}
+
+/// A node of a symbol tree, as far as qualified names need it.
+trait Nested: Sized {
+    fn name(&self) -> &str;
+    fn children(&self) -> &[Self];
+}
+
+impl Nested for SymbolNode {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn children(&self) -> &[Self] {
+        &self.children
+    }
+}
+
+/// Symbols under `symbols`, at any depth, whose qualified name ends with
+/// `wanted`, paired with that name. `prefix` is the enclosing module path.
+fn collect<'a, T: Nested>(
+    symbols: &'a [T],
+    prefix: Vec<String>,
+    wanted: &[String],
+    out: &mut Vec<(String, &'a T)>,
+) {
+    for symbol in symbols {
+        let mut path = prefix.clone();
+        path.push(symbol.name().to_string());
+        if path.ends_with(wanted) {
+            out.push((path.join("::"), symbol));
+        }
+        collect(symbol.children(), path, wanted, out);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SimilarQuery {
+    limit: Option<usize>,
+}
+
+/// Symbols whose names and signatures share the most words with symbol
+/// `id`, best first; empty when none reach [`matching::MIN_SIMILARITY`].
+pub async fn similar(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<SimilarQuery>,
+) -> impl IntoResponse {
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi else {
+        return indexing::not_initialized(&state);
+    };
+
+    let target =
+        match blocking::query(&adi, move |adi| adi.get_symbol(adi_core::SymbolId(id))).await {
+            Ok(symbol) => symbol,
+            Err(e) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({ "error": e.to_string() })),
+                );
+            }
+        };
+
+    let limit = query.limit.unwrap_or(DEFAULT_SIMILAR_LIMIT);
+    // Compares against every symbol, so it runs off the runtime and stops with the client
+    let scored = blocking::run(move |cancel| {
+        let tree = adi.get_tree()?;
+        let target_tokens = matching::tokens(&target);
+        let mut scored: Vec<ScoredSymbol> = catalog::symbols(&adi, &tree)
+            .take_while(|_| !cancel.is_cancelled())
+            .filter(|s| s.id != target.id)
+            .filter_map(|symbol| {
+                let score = matching::similarity(&target, &target_tokens, &symbol);
+                (score >= matching::MIN_SIMILARITY).then_some(ScoredSymbol { symbol, score })
+            })
+            .collect();
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(limit);
+        adi_core::Result::Ok(scored)
+    })
+    .await;
+
+    match scored {
+        Ok(scored) => (StatusCode::OK, Json(serde_json::to_value(scored).unwrap())),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FileDiffQuery {
+    /// Project-relative paths of the two files to compare
+    a: String,
+    b: String,
+}
+
+#[derive(Serialize)]
+struct Declared {
+    id: adi_core::SymbolId,
+    name: String,
+    kind: adi_core::SymbolKind,
+    signature: Option<String>,
+}
+
+impl Declared {
+    fn of(symbol: adi_core::Symbol) -> Self {
+        Self {
+            id: symbol.id,
+            name: symbol.name,
+            kind: symbol.kind,
+            signature: symbol.signature,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Changed {
+    name: String,
+    kind: adi_core::SymbolKind,
+    a: Declared,
+    b: Declared,
+}
+
+#[derive(Default, Serialize)]
+struct FileDiff {
+    only_in_a: Vec<Declared>,
+    only_in_b: Vec<Declared>,
+    changed: Vec<Changed>,
+    unchanged: usize,
+}
+
+/// Symbols of file `a` and file `b` matched by name and kind: those only
+/// one of them declares, and those both declare with different signatures.
+pub async fn file_diff(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FileDiffQuery>,
+) -> impl IntoResponse {
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi.as_ref() else {
+        return indexing::not_initialized(&state);
+    };
+
+    let a = PathBuf::from(catalog::normalize_scope(&query.a));
+    let b = PathBuf::from(catalog::normalize_scope(&query.b));
+    let files = blocking::query(adi, move |adi| {
+        let a = adi.get_file(&a).map_err(|_| a)?;
+        let b = adi.get_file(&b).map_err(|_| b)?;
+        Ok::<_, PathBuf>((a.symbols, b.symbols))
+    })
+    .await;
+
+    match files {
+        Ok((a, b)) => {
+            let diff = diff_files(a, b);
+            (StatusCode::OK, Json(serde_json::to_value(diff).unwrap()))
+        }
+        Err(missing) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": format!("File not indexed: {}", missing.display())
+            })),
+        ),
+    }
+}
+
+/// Pairs same-named symbols of a kind in declaration order; any left over
+/// on either side count as missing from the other.
+fn diff_files(a: Vec<adi_core::Symbol>, b: Vec<adi_core::Symbol>) -> FileDiff {
+    let paired = pair(
+        a,
+        b,
+        |s| (s.name.clone(), catalog::kind_name(&s.kind)),
+        |s| s.signature.as_deref(),
+    );
+    FileDiff {
+        only_in_a: paired.only_in_a.into_iter().map(Declared::of).collect(),
+        only_in_b: paired.only_in_b.into_iter().map(Declared::of).collect(),
+        changed: paired
+            .changed
+            .into_iter()
+            .map(|(a, b)| Changed {
+                name: a.name.clone(),
+                kind: a.kind,
+                a: Declared::of(a),
+                b: Declared::of(b),
+            })
+            .collect(),
+        unchanged: paired.unchanged,
+    }
+}
+
+/// Declarations of two files matched up by [`pair`].
+struct Paired<T> {
+    only_in_a: Vec<T>,
+    only_in_b: Vec<T>,
+    /// Same key, different signatures
+    changed: Vec<(T, T)>,
+    unchanged: usize,
+}
+
+/// Matches each of `a` with the first unmatched one of `b` sharing its
+/// `key`, in order. Signatures compare ignoring whitespace.
+fn pair<T, K: Ord>(
+    a: Vec<T>,
+    b: Vec<T>,
+    key: impl Fn(&T) -> K,
+    signature: impl Fn(&T) -> Option<&str>,
+) -> Paired<T> {
+    let mut unmatched: BTreeMap<K, Vec<T>> = BTreeMap::new();
+    for item in b {
+        unmatched.entry(key(&item)).or_default().push(item);
+    }
+    for candidates in unmatched.values_mut() {
+        candidates.reverse();
+    }
+
+    let mut paired = Paired {
+        only_in_a: Vec::new(),
+        only_in_b: Vec::new(),
+        changed: Vec::new(),
+        unchanged: 0,
+    };
+    for item in a {
+        let Some(other) = unmatched.get_mut(&key(&item)).and_then(Vec::pop) else {
+            paired.only_in_a.push(item);
+            continue;
+        };
+        let same = match (signature(&item), signature(&other)) {
+            (Some(a), Some(b)) => {
+                let strip = |s: &str| s.split_whitespace().collect::<String>();
+                strip(a) == strip(b)
+            }
+            (a, b) => a == b,
+        };
+        if same {
+            paired.unchanged += 1;
+        } else {
+            paired.changed.push((item, other));
+        }
+    }
+    paired.only_in_b = unmatched
+        .into_values()
+        .flat_map(|rest| rest.into_iter().rev())
+        .collect();
+    paired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn segments_split_on_both_separators() {
+        assert_eq!(
+            segments("crate::config.Config::from_env"),
+            owned(&["crate", "config", "Config", "from_env"])
+        );
+    }
+
+    #[test]
+    fn segments_drop_empty_parts() {
+        assert!(segments("").is_empty());
+        assert_eq!(segments("::a..b::"), owned(&["a", "b"]));
+    }
+
+    #[test]
+    fn module_path_of_an_empty_path_is_the_crate() {
+        assert_eq!(module_path(FsPath::new("")), owned(&["crate"]));
+    }
+
+    #[test]
+    fn module_path_skips_the_leading_src() {
+        assert_eq!(
+            module_path(FsPath::new("src/config.rs")),
+            owned(&["crate", "config"])
+        );
+        assert_eq!(
+            module_path(FsPath::new("tools/src/gen.rs")),
+            owned(&["crate", "tools", "src", "gen"])
+        );
+    }
+
+    #[test]
+    fn module_path_names_nested_modules() {
+        assert_eq!(
+            module_path(FsPath::new("src/server/http/routes.rs")),
+            owned(&["crate", "server", "http", "routes"])
+        );
+    }
+
+    #[test]
+    fn module_roots_name_their_directory() {
+        assert_eq!(module_path(FsPath::new("src/lib.rs")), owned(&["crate"]));
+        assert_eq!(
+            module_path(FsPath::new("src/config/mod.rs")),
+            owned(&["crate", "config"])
+        );
+        assert_eq!(
+            module_path(FsPath::new("app/models/__init__.py")),
+            owned(&["crate", "app", "models"])
+        );
+    }
+
+    struct Node {
+        name: &'static str,
+        children: Vec<Node>,
+    }
+
+    impl Nested for Node {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn children(&self) -> &[Self] {
+            &self.children
+        }
+    }
+
+    fn node(name: &'static str, children: Vec<Node>) -> Node {
+        Node { name, children }
+    }
+
+    fn qualified(symbols: &[Node], wanted: &str) -> Vec<String> {
+        let mut found = Vec::new();
+        collect(
+            symbols,
+            owned(&["crate", "config"]),
+            &segments(wanted),
+            &mut found,
+        );
+        found.into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn collect_finds_nested_symbols_by_suffix() {
+        let tree = [node(
+            "Config",
+            vec![node("from_env", vec![]), node("load", vec![])],
+        )];
+        assert_eq!(
+            qualified(&tree, "Config::from_env"),
+            ["crate::config::Config::from_env"]
+        );
+        assert_eq!(
+            qualified(&tree, "crate::config::Config"),
+            ["crate::config::Config"]
+        );
+        assert!(qualified(&tree, "Config::save").is_empty());
+    }
+
+    #[test]
+    fn collect_keeps_every_match() {
+        let tree = [
+            node("A", vec![node("new", vec![])]),
+            node("B", vec![node("new", vec![])]),
+        ];
+        assert_eq!(
+            qualified(&tree, "new"),
+            ["crate::config::A::new", "crate::config::B::new"]
+        );
+    }
+
+    type Decl = (&'static str, Option<&'static str>);
+
+    fn pair_decls(a: Vec<Decl>, b: Vec<Decl>) -> Paired<Decl> {
+        pair(a, b, |d| d.0, |d| d.1)
+    }
+
+    #[test]
+    fn pair_finds_a_renamed_file_unchanged() {
+        let symbols = vec![("new", Some("fn new() -> Self")), ("Config", None)];
+        let paired = pair_decls(symbols.clone(), symbols);
+        assert_eq!(paired.unchanged, 2);
+        assert!(paired.changed.is_empty());
+        assert!(paired.only_in_a.is_empty() && paired.only_in_b.is_empty());
+    }
+
+    #[test]
+    fn pair_ignores_whitespace_in_signatures() {
+        let paired = pair_decls(
+            vec![("load", Some("fn load(path: &Path)"))],
+            vec![("load", Some("fn load( path:&Path )"))],
+        );
+        assert_eq!(paired.unchanged, 1);
+    }
+
+    #[test]
+    fn pair_reports_changed_and_missing_symbols() {
+        let paired = pair_decls(
+            vec![("load", Some("fn load()")), ("save", None)],
+            vec![("load", Some("fn load(force: bool)")), ("open", None)],
+        );
+        assert_eq!(paired.changed.len(), 1);
+        assert_eq!(paired.only_in_a, [("save", None)]);
+        assert_eq!(paired.only_in_b, [("open", None)]);
+    }
+
+    #[test]
+    fn pair_matches_overloads_in_declaration_order() {
+        let paired = pair_decls(
+            vec![("f", Some("fn f(a)")), ("f", Some("fn f(b)"))],
+            vec![("f", Some("fn f(a)")), ("f", Some("fn f(c)")), ("f", None)],
+        );
+        assert_eq!(paired.unchanged, 1);
+        assert_eq!(
+            paired.changed,
+            [(("f", Some("fn f(b)")), ("f", Some("fn f(c)")))]
+        );
+        assert_eq!(paired.only_in_b, [("f", None)]);
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Server-side syntax highlighting of symbol source, for frontends without
+//! a highlighter of their own.
+
+use crate::{blocking, files, indexing, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// Used when a request doesn't pick a `theme`.
+const DEFAULT_THEME: &str = "InspiredGitHub";
+
+/// Class names in the fragment and the CSS carry this prefix, so they
+/// can't collide with the embedding page's own.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// Loaded on first use; parsing the bundled definitions takes a while.
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+fn syntaxes() -> &'static SyntaxSet {
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+#[derive(Deserialize)]
+pub struct HighlightQuery {
+    /// One of syntect's bundled themes, e.g. `base16-ocean.dark`
+    theme: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Highlighted {
+    /// `<span>`s with classes only; pair it with `css`
+    html: String,
+    /// Rules giving the classes in `html` the theme's colors
+    css: String,
+    theme: String,
+    /// Syntax the source was highlighted as, `Plain Text` when unknown
+    syntax: String,
+}
+
+/// Symbol `id`'s source as classed HTML spans plus the theme's stylesheet.
+pub async fn symbol(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<HighlightQuery>,
+) -> impl IntoResponse {
+    let theme_name = query.theme.unwrap_or_else(|| DEFAULT_THEME.to_string());
+    let Some(theme) = themes().themes.get(&theme_name) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Unknown theme: {}", theme_name),
+                "themes": themes().themes.keys().collect::<Vec<_>>(),
+            })),
+        );
+    };
+
+    let adi = match state.read_adi().await {
+        Ok(adi) => adi,
+        Err(rejection) => return rejection,
+    };
+    let Some(adi) = adi.as_ref() else {
+        return indexing::not_initialized(&state);
+    };
+
+    let symbol = match blocking::query(adi, move |adi| adi.get_symbol(adi_core::SymbolId(id))).await
+    {
+        Ok(symbol) => symbol,
+        Err(e) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            );
+        }
+    };
+    let Some(source) = files::read_source(&state.project_path(), &symbol).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": format!("Source not readable: {}", symbol.file_path.display())
+            })),
+        );
+    };
+
+    let css = match css_for_theme_with_class_style(theme, CLASS_STYLE) {
+        Ok(css) => css,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            );
+        }
+    };
+    let extension = symbol
+        .file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let (syntax, html) = blocking::run(move |_| highlight(&source, &extension)).await;
+
+    match html {
+        Ok(html) => {
+            let body = Highlighted {
+                html,
+                css,
+                theme: theme_name,
+                syntax,
+            };
+            (StatusCode::OK, Json(serde_json::to_value(body).unwrap()))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
+}
+
+/// `source` as classed spans, parsed by the syntax for `extension`.
+fn highlight(source: &str, extension: &str) -> (String, Result<String, syntect::Error>) {
+    let syntaxes = syntaxes();
+    let syntax = syntaxes
+        .find_syntax_by_extension(extension)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, CLASS_STYLE);
+    let html = LinesWithEndings::from(source)
+        .try_for_each(|line| generator.parse_html_for_line_which_includes_newline(line))
+        .map(|()| generator.finalize());
+    (syntax.name.clone(), html)
+}
@@ -0,0 +1,223 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use anyhow::Result;
+use axum::body::HttpBody;
+use axum::extract::{MatchedPath, Query};
+use axum::http::{HeaderMap, HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+use tower_http::trace::DefaultOnResponse;
+use tower_http::LatencyUnit;
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+/// Set once OTLP export is configured; request spans only pay for
+/// `traceparent` extraction when this is present.
+static PROVIDER: OnceLock<TracerProvider> = OnceLock::new();
+
+/// Whether `LOG_FORMAT=json` was chosen.
+static JSON_LOGS: OnceLock<bool> = OnceLock::new();
+
+/// Query parameters whose values never reach the access log.
+const SECRET_PARAMS: [&str; 4] = ["token", "key", "secret", "password"];
+
+/// Request ids for requests that don't bring an `x-request-id`.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Flushes pending spans when dropped at the end of `main`.
+pub struct TelemetryGuard;
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = PROVIDER.get() {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!("Failed to flush OpenTelemetry spans: {}", e);
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber. Logs are human-readable unless
+/// `LOG_FORMAT=json`; OTLP export is enabled only when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+pub fn init() -> Result<TelemetryGuard> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let format = std::env::var("LOG_FORMAT").unwrap_or_default();
+    let json = format.eq_ignore_ascii_case("json");
+    let _ = JSON_LOGS.set(json);
+
+    let otel = match std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+        Ok(endpoint) if !endpoint.is_empty() => {
+            // The exporter reads the endpoint (and OTEL_EXPORTER_OTLP_* options) itself
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .build()?;
+            let provider = TracerProvider::builder()
+                .with_batch_exporter(exporter, runtime::Tokio)
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    env!("CARGO_PKG_NAME"),
+                )]))
+                .build();
+            let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+            opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+            opentelemetry::global::set_tracer_provider(provider.clone());
+            let _ = PROVIDER.set(provider);
+
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        _ => None,
+    };
+
+    // Span fields (request id, route) become part of every JSON line
+    let json_layer = json.then(|| {
+        fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+    });
+    let pretty_layer = (!json).then(fmt::layer);
+
+    tracing_subscriber::registry()
+        .with(json_layer)
+        .with(pretty_layer)
+        .with(otel)
+        .with(filter)
+        .init();
+
+    if !json && !format.is_empty() && !format.eq_ignore_ascii_case("pretty") {
+        tracing::warn!("Ignoring unknown LOG_FORMAT={:?}, using pretty", format);
+    }
+    if PROVIDER.get().is_some() {
+        tracing::info!("OpenTelemetry OTLP export enabled");
+    }
+
+    Ok(TelemetryGuard)
+}
+
+/// Gives a request without an `x-request-id` a generated one, so its log
+/// lines and any error body name the same id.
+pub async fn request_id(mut request: axum::extract::Request, next: Next) -> Response {
+    if !request.headers().contains_key("x-request-id") {
+        let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        request
+            .headers_mut()
+            .insert("x-request-id", HeaderValue::from(id));
+    }
+    next.run(request).await
+}
+
+/// Span for `TraceLayer` that joins the caller's trace when a
+/// `traceparent` header is present.
+pub fn make_request_span<B>(request: &Request<B>) -> Span {
+    let request_id = match request
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(id) => id.to_string(),
+        None => NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed).to_string(),
+    };
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str);
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+        route,
+        version = ?request.version(),
+    );
+
+    if PROVIDER.get().is_some() {
+        let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(request.headers()))
+        });
+        span.set_parent(parent);
+    }
+
+    span
+}
+
+/// Response logging for `TraceLayer`: status and latency per request, at
+/// `info` for JSON logs so pipelines get them by default.
+pub fn on_response() -> DefaultOnResponse {
+    let level = if JSON_LOGS.get().copied().unwrap_or_default() {
+        Level::INFO
+    } else {
+        Level::DEBUG
+    };
+    DefaultOnResponse::new()
+        .level(level)
+        .latency_unit(LatencyUnit::Millis)
+}
+
+/// One `access` line per request: method, path, decoded query parameters,
+/// status, body size (when known up front) and latency. Credentials in the
+/// query are redacted; headers aren't logged at all.
+pub async fn access_log(request: axum::extract::Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let query = Query::<Vec<(String, String)>>::try_from_uri(request.uri())
+        .map(|Query(params)| redact(params))
+        .unwrap_or_default();
+
+    let response = next.run(request).await;
+
+    tracing::info!(
+        target: "access",
+        method = %method,
+        path,
+        query = %query,
+        status = response.status().as_u16(),
+        bytes = response.body().size_hint().exact(),
+        latency_ms = started.elapsed().as_millis() as u64,
+    );
+    response
+}
+
+fn redact(params: Vec<(String, String)>) -> serde_json::Value {
+    params
+        .into_iter()
+        .map(|(name, value)| {
+            let lower = name.to_lowercase();
+            let value = if SECRET_PARAMS.iter().any(|s| lower.contains(s)) {
+                "[redacted]".to_string()
+            } else {
+                value
+            };
+            (name, serde_json::Value::String(value))
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
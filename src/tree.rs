@@ -0,0 +1,338 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Directory structure derived from adi_core's flat file list.
+
+use crate::catalog;
+use crate::packages::{self, Package};
+use adi_core::{FileNode, SymbolNode, Tree};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Key used for the project root in directory maps.
+pub const ROOT: &str = ".";
+
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct DirCounts {
+    pub files: usize,
+    pub symbols: usize,
+}
+
+/// Files and symbols beneath every directory, including nested ones.
+pub fn directory_counts(tree: &Tree) -> BTreeMap<String, DirCounts> {
+    let mut counts: BTreeMap<String, DirCounts> = BTreeMap::new();
+
+    for file in &tree.files {
+        let symbols = count_symbols(&file.symbols);
+        for dir in ancestors(&file.path) {
+            let entry = counts.entry(dir).or_default();
+            entry.files += 1;
+            entry.symbols += symbols;
+        }
+    }
+
+    counts
+}
+
+/// Directories containing `path`, outermost first, starting with [`ROOT`].
+pub fn ancestors(path: &std::path::Path) -> Vec<String> {
+    let mut dirs = vec![ROOT.to_string()];
+    let mut current = std::path::PathBuf::new();
+
+    if let Some(parent) = path.parent() {
+        for component in parent.components() {
+            current.push(component);
+            dirs.push(current.to_string_lossy().into_owned());
+        }
+    }
+
+    dirs
+}
+
+fn count_symbols(symbols: &[SymbolNode]) -> usize {
+    symbols.iter().map(|s| 1 + count_symbols(&s.children)).sum()
+}
+
+/// Stable id of a directory node, derived from its path.
+pub fn directory_id(path: &str) -> String {
+    format!("d-{:016x}", fnv1a(path))
+}
+
+/// Stable id of a file node, derived from its path.
+pub fn file_id(path: &Path) -> String {
+    format!("f-{:016x}", fnv1a(&path.to_string_lossy()))
+}
+
+/// FNV-1a, so ids survive restarts and reindexes unlike `DefaultHasher`.
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Directory directly containing `path`.
+pub fn parent_dir(path: &Path) -> String {
+    ancestors(path).pop().unwrap_or_else(|| ROOT.to_string())
+}
+
+/// One entry of a lazily expanded tree.
+#[derive(Serialize)]
+pub struct Node {
+    /// Directory and file ids are path hashes; symbols use their symbol id
+    pub id: String,
+    pub kind: String,
+    pub name: String,
+    pub path: String,
+    /// Path of the containing directory or file
+    pub parent: String,
+    pub has_children: bool,
+}
+
+impl Node {
+    fn directory(path: &str, parent: &str) -> Self {
+        Self {
+            id: directory_id(path),
+            kind: "directory".to_string(),
+            name: Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.to_string()),
+            path: path.to_string(),
+            parent: parent.to_string(),
+            has_children: true,
+        }
+    }
+
+    fn file(file: &FileNode) -> Self {
+        Self {
+            id: file_id(&file.path),
+            kind: "file".to_string(),
+            name: file
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: file.path.to_string_lossy().into_owned(),
+            parent: parent_dir(&file.path),
+            has_children: !file.symbols.is_empty(),
+        }
+    }
+
+    fn symbol(file: &FileNode, symbol: &SymbolNode) -> Self {
+        let path = file.path.to_string_lossy().into_owned();
+        Self {
+            id: symbol.id.0.to_string(),
+            kind: catalog::kind_name(&symbol.kind),
+            name: symbol.name.clone(),
+            parent: path.clone(),
+            path,
+            has_children: !symbol.children.is_empty(),
+        }
+    }
+}
+
+/// Direct children of node `id`, or `None` if no node has that id.
+pub fn children(tree: &Tree, id: &str) -> Option<Vec<Node>> {
+    if let Ok(symbol_id) = id.parse::<i64>() {
+        let (file, symbol) = catalog::symbol_nodes(tree)
+            .into_iter()
+            .find(|(_, symbol)| symbol.id.0 == symbol_id)?;
+        return Some(
+            symbol
+                .children
+                .iter()
+                .map(|child| Node::symbol(file, child))
+                .collect(),
+        );
+    }
+
+    if id.starts_with("f-") {
+        let file = tree.files.iter().find(|f| file_id(&f.path) == id)?;
+        return Some(file.symbols.iter().map(|s| Node::symbol(file, s)).collect());
+    }
+
+    let directories: BTreeSet<String> = tree
+        .files
+        .iter()
+        .flat_map(|file| ancestors(&file.path))
+        .collect();
+    let dir = directories.iter().find(|d| directory_id(d) == id)?;
+
+    let subdirs = directories
+        .iter()
+        .filter(|d| *d != dir && parent_dir(Path::new(d.as_str())) == *dir)
+        .map(|d| Node::directory(d, dir));
+    let files = tree
+        .files
+        .iter()
+        .filter(|f| parent_dir(&f.path) == *dir)
+        .map(Node::file);
+
+    Some(subdirs.chain(files).collect())
+}
+
+/// One line of `/tree?stream=true`. Nodes arrive depth first, so `parent`
+/// always names a node already sent; it's `null` only for the root.
+#[derive(Serialize)]
+pub struct StreamedNode {
+    pub id: String,
+    pub parent: Option<String>,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_kind: Option<String>,
+    pub name: String,
+    pub path: String,
+}
+
+/// Visits every directory, file and symbol of `tree` depth first: each
+/// directory's subdirectories, then its files, each file followed by its
+/// symbols. Stops early once `emit` returns `false`.
+pub fn walk(tree: &Tree, mut emit: impl FnMut(StreamedNode) -> bool) {
+    let mut subdirs: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut files: BTreeMap<String, Vec<&FileNode>> = BTreeMap::new();
+    for file in &tree.files {
+        let dirs = ancestors(&file.path);
+        for pair in dirs.windows(2) {
+            subdirs
+                .entry(pair[0].clone())
+                .or_default()
+                .insert(pair[1].clone());
+        }
+        files.entry(parent_dir(&file.path)).or_default().push(file);
+    }
+
+    let root = Node::directory(ROOT, ROOT);
+    if emit(StreamedNode::from(root, None, None)) {
+        walk_dir(ROOT, &subdirs, &files, &mut emit);
+    }
+}
+
+fn walk_dir(
+    dir: &str,
+    subdirs: &BTreeMap<String, BTreeSet<String>>,
+    files: &BTreeMap<String, Vec<&FileNode>>,
+    emit: &mut impl FnMut(StreamedNode) -> bool,
+) -> bool {
+    let parent = directory_id(dir);
+    for sub in subdirs.get(dir).into_iter().flatten() {
+        let node = Node::directory(sub, dir);
+        if !emit(StreamedNode::from(node, Some(parent.clone()), None))
+            || !walk_dir(sub, subdirs, files, emit)
+        {
+            return false;
+        }
+    }
+    for file in files.get(dir).into_iter().flatten() {
+        let node = Node::file(file);
+        if !emit(StreamedNode::from(node, Some(parent.clone()), None))
+            || !walk_symbols(file, &file.symbols, &file_id(&file.path), emit)
+        {
+            return false;
+        }
+    }
+    true
+}
+
+fn walk_symbols(
+    file: &FileNode,
+    symbols: &[SymbolNode],
+    parent: &str,
+    emit: &mut impl FnMut(StreamedNode) -> bool,
+) -> bool {
+    symbols.iter().all(|symbol| {
+        let node = Node::symbol(file, symbol);
+        let id = node.id.clone();
+        // Streamed lines skip `format::canonical_kinds`, so name kinds here
+        let canonical = catalog::canonical_kind(&node.kind).map(str::to_string);
+        emit(StreamedNode::from(
+            node,
+            Some(parent.to_string()),
+            canonical,
+        )) && walk_symbols(file, &symbol.children, &id, emit)
+    })
+}
+
+impl StreamedNode {
+    fn from(node: Node, parent: Option<String>, canonical_kind: Option<String>) -> Self {
+        let (kind, raw_kind) = match canonical_kind {
+            Some(canonical) => (canonical, Some(node.kind)),
+            None => (node.kind, None),
+        };
+        Self {
+            id: node.id,
+            parent,
+            kind,
+            raw_kind,
+            name: node.name,
+            path: node.path,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FileGroup {
+    /// Package name, or the directory when grouping falls back to directories
+    pub name: Option<String>,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    pub files: Vec<serde_json::Value>,
+}
+
+pub struct Grouping {
+    /// `"crate"`, or `"directory"` when no manifests were found
+    pub kind: &'static str,
+    pub groups: Vec<FileGroup>,
+}
+
+/// Serialized file nodes (`files`, parallel to `tree.files`) nested under
+/// their owning package, or their directory when there are no packages.
+pub fn group_files(tree: &Tree, files: serde_json::Value, packages: &[Package]) -> Grouping {
+    let files = match files {
+        serde_json::Value::Array(files) => files,
+        _ => Vec::new(),
+    };
+
+    let mut groups: BTreeMap<String, FileGroup> = BTreeMap::new();
+    for (node, file) in files.into_iter().zip(&tree.files) {
+        let group = if packages.is_empty() {
+            let dir = parent_dir(&file.path);
+            groups.entry(dir.clone()).or_insert_with(|| FileGroup {
+                name: Some(dir.clone()),
+                path: dir,
+                manifest: None,
+                files: Vec::new(),
+            })
+        } else {
+            match packages::owner(packages, &file.path) {
+                Some(package) => groups
+                    .entry(package.path.clone())
+                    .or_insert_with(|| FileGroup {
+                        name: Some(package.name.clone()),
+                        path: package.path.clone(),
+                        manifest: Some(package.manifest.clone()),
+                        files: Vec::new(),
+                    }),
+                // Files outside every package
+                None => groups.entry(String::new()).or_insert_with(|| FileGroup {
+                    name: None,
+                    path: ROOT.to_string(),
+                    manifest: None,
+                    files: Vec::new(),
+                }),
+            }
+        };
+        group.files.push(node);
+    }
+
+    Grouping {
+        kind: if packages.is_empty() {
+            "directory"
+        } else {
+            "crate"
+        },
+        groups: groups.into_values().collect(),
+    }
+}
@@ -0,0 +1,13 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! A single-page search UI for demos and debugging, compiled into the binary.
+
+use axum::response::Html;
+
+const PAGE: &str = include_str!("ui.html");
+
+pub async fn page() -> Html<&'static str> {
+    Html(PAGE)
+}
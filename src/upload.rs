@@ -0,0 +1,309 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Unpacking uploaded project snapshots for `/admin/index/upload`.
+
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+use tempfile::TempDir;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    TarGz,
+    Zip,
+}
+
+/// Caps on what one archive may unpack to, so a small upload can't expand
+/// into one that fills the disk.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Total size of the extracted files
+    pub max_bytes: u64,
+    /// Files and directories together
+    pub max_entries: usize,
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    /// The archive unpacks past [`Limits`]
+    TooLarge(String),
+    /// Not a readable archive, or unpacking it failed
+    Invalid(io::Error),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge(reason) => f.write_str(reason),
+            Self::Invalid(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for ExtractError {
+    fn from(e: io::Error) -> Self {
+        Self::Invalid(e)
+    }
+}
+
+impl From<zip::result::ZipError> for ExtractError {
+    fn from(e: zip::result::ZipError) -> Self {
+        Self::Invalid(e.into())
+    }
+}
+
+/// Archive format from the body's magic bytes.
+pub fn detect(bytes: &[u8]) -> Option<Format> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        Some(Format::TarGz)
+    } else if bytes.starts_with(b"PK\x03\x04") {
+        Some(Format::Zip)
+    } else {
+        None
+    }
+}
+
+/// Extracts `bytes` into a new temporary directory, deleted when dropped,
+/// stopping as soon as the archive exceeds `limits`. Entries that would land
+/// outside the directory are refused, and so are links, which could point
+/// there.
+pub fn extract(bytes: &[u8], format: Format, limits: Limits) -> Result<TempDir, ExtractError> {
+    let dir = tempfile::Builder::new().prefix("adi-upload-").tempdir()?;
+    let mut budget = Budget {
+        limits,
+        bytes: 0,
+        entries: 0,
+    };
+
+    match format {
+        Format::TarGz => unpack_tar(bytes, dir.path(), &mut budget)?,
+        Format::Zip => unpack_zip(bytes, dir.path(), &mut budget)?,
+    }
+
+    Ok(dir)
+}
+
+/// What has been unpacked so far, checked against [`Limits`] before each
+/// entry is written.
+struct Budget {
+    limits: Limits,
+    bytes: u64,
+    entries: usize,
+}
+
+impl Budget {
+    /// Accounts for one more entry of `size` bytes.
+    fn spend(&mut self, size: u64) -> Result<(), ExtractError> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            return Err(ExtractError::TooLarge(format!(
+                "Archive has more than {} entries",
+                self.limits.max_entries
+            )));
+        }
+        self.bytes = self.bytes.saturating_add(size);
+        if self.bytes > self.limits.max_bytes {
+            return Err(ExtractError::TooLarge(format!(
+                "Archive unpacks to more than {} bytes",
+                self.limits.max_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn unpack_tar(bytes: &[u8], to: &Path, budget: &mut Budget) -> Result<(), ExtractError> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bytes));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        // Sparse files unpack to more than the archive stores for them
+        if kind.is_gnu_sparse() {
+            return Err(invalid("Sparse files are not supported"));
+        }
+        // Links are followed when sources are read, and may point anywhere
+        if kind.is_symlink() || kind.is_hard_link() {
+            return Err(invalid(&format!(
+                "Entry {:?} is a link; links are not supported",
+                String::from_utf8_lossy(&entry.path_bytes())
+            )));
+        }
+        budget.spend(entry.size())?;
+        // Skips entries that would land outside `to`
+        entry.unpack_in(to)?;
+    }
+    Ok(())
+}
+
+fn unpack_zip(bytes: &[u8], to: &Path, budget: &mut Budget) -> Result<(), ExtractError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let Some(relative) = file.enclosed_name() else {
+            return Err(invalid(&format!(
+                "Entry {:?} leaves the archive",
+                file.name()
+            )));
+        };
+        if file.is_symlink() {
+            return Err(invalid(&format!(
+                "Entry {:?} is a link; links are not supported",
+                file.name()
+            )));
+        }
+        let path = to.join(relative);
+        if file.is_dir() {
+            budget.spend(0)?;
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+
+        let size = file.size();
+        budget.spend(size)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out = std::fs::File::create(&path)?;
+        // The declared size is what was budgeted; never write past it
+        let written = io::copy(&mut (&mut file).take(size.saturating_add(1)), &mut out)?;
+        if written > size {
+            return Err(ExtractError::TooLarge(format!(
+                "Entry {:?} is larger than it declares",
+                file.name()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn invalid(reason: &str) -> ExtractError {
+    ExtractError::Invalid(io::Error::new(io::ErrorKind::InvalidData, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const ROOMY: Limits = Limits {
+        max_bytes: 1024,
+        max_entries: 10,
+    };
+
+    fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(gz);
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, data) in files {
+            let options = zip::write::SimpleFileOptions::default();
+            writer.start_file(*path, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn assert_invalid(outcome: Result<TempDir, ExtractError>) {
+        match outcome {
+            Err(ExtractError::Invalid(_)) => {}
+            Err(e) => panic!("expected Invalid, got {}", e),
+            Ok(_) => panic!("expected Invalid, got an extracted archive"),
+        }
+    }
+
+    fn assert_too_large(outcome: Result<TempDir, ExtractError>) {
+        match outcome {
+            Err(ExtractError::TooLarge(_)) => {}
+            Err(e) => panic!("expected TooLarge, got {}", e),
+            Ok(_) => panic!("expected TooLarge, got an extracted archive"),
+        }
+    }
+
+    #[test]
+    fn extracts_archives_within_limits() {
+        let files: [(&str, &[u8]); 2] = [("src/lib.rs", b"fn a() {}"), ("README", b"hi")];
+        for (bytes, format) in [(tar_gz(&files), Format::TarGz), (zip(&files), Format::Zip)] {
+            let dir = extract(&bytes, format, ROOMY).unwrap();
+            let lib = std::fs::read(dir.path().join("src/lib.rs")).unwrap();
+            assert_eq!(lib, b"fn a() {}");
+        }
+    }
+
+    #[test]
+    fn refuses_archives_with_too_many_entries() {
+        let limits = Limits {
+            max_entries: 2,
+            ..ROOMY
+        };
+        let files: [(&str, &[u8]); 3] = [("a", b""), ("b", b""), ("c", b"")];
+        assert_too_large(extract(&tar_gz(&files), Format::TarGz, limits));
+        assert_too_large(extract(&zip(&files), Format::Zip, limits));
+    }
+
+    #[test]
+    fn refuses_archives_unpacking_past_the_byte_limit() {
+        let limits = Limits {
+            max_bytes: 100,
+            ..ROOMY
+        };
+        // Compresses to far less than it unpacks to
+        let zeros = [0u8; 101];
+        let files: [(&str, &[u8]); 1] = [("zeros", &zeros)];
+        assert_too_large(extract(&tar_gz(&files), Format::TarGz, limits));
+        assert_too_large(extract(&zip(&files), Format::Zip, limits));
+    }
+
+    #[test]
+    fn counts_bytes_across_entries() {
+        let limits = Limits {
+            max_bytes: 10,
+            ..ROOMY
+        };
+        let files: [(&str, &[u8]); 2] = [("a", b"123456"), ("b", b"123456")];
+        assert_too_large(extract(&tar_gz(&files), Format::TarGz, limits));
+        assert_too_large(extract(&zip(&files), Format::Zip, limits));
+    }
+
+    #[test]
+    fn refuses_tar_links_out_of_the_archive() {
+        for kind in [tar::EntryType::Symlink, tar::EntryType::Link] {
+            let gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            let mut builder = tar::Builder::new(gz);
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(kind);
+            header.set_size(0);
+            header.set_mode(0o777);
+            builder
+                .append_link(&mut header, "passwd", "../../etc/passwd")
+                .unwrap();
+            let bytes = builder.into_inner().unwrap().finish().unwrap();
+            assert_invalid(extract(&bytes, Format::TarGz, ROOMY));
+        }
+    }
+
+    #[test]
+    fn refuses_zip_symlinks() {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .add_symlink(
+                "passwd",
+                "../../etc/passwd",
+                zip::write::SimpleFileOptions::default(),
+            )
+            .unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+        assert_invalid(extract(&bytes, Format::Zip, ROOMY));
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! API version negotiation. A request picks a version with a `/v1` path
+//! prefix or `Accept: application/vnd.adi.v1+json`; unversioned requests
+//! get [`DEFAULT`].
+
+use axum::{
+    extract::Request,
+    http::{header, uri::PathAndQuery, HeaderValue, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+/// Versions this build can answer in. A new version is added here when a
+/// response shape changes incompatibly; old ones stay until retired.
+pub const SUPPORTED: [u32; 1] = [1];
+
+/// Version for requests that don't ask for one.
+pub const DEFAULT: u32 = 1;
+
+/// Resolves the requested version, strips any `/vN` prefix so routing sees
+/// the plain path, and names the version served in `X-API-Version`.
+pub async fn negotiate(mut request: Request, next: Next) -> Response {
+    let from_path = path_version(request.uri().path());
+    let from_accept = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .and_then(accept_version);
+
+    let version = match (from_path, from_accept) {
+        (Some(path), Some(accept)) if path != accept => {
+            return reject(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Path asks for API v{} but Accept asks for v{}",
+                    path, accept
+                ),
+            );
+        }
+        (Some(version), _) | (None, Some(version)) => version,
+        (None, None) => DEFAULT,
+    };
+    if !SUPPORTED.contains(&version) {
+        return reject(
+            StatusCode::NOT_ACCEPTABLE,
+            format!("Unsupported API version: v{}", version),
+        );
+    }
+
+    if from_path.is_some() {
+        *request.uri_mut() = strip_prefix(request.uri());
+    }
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .insert("x-api-version", HeaderValue::from(version));
+    response
+}
+
+fn reject(status: StatusCode, error: String) -> Response {
+    (
+        status,
+        Json(serde_json::json!({ "error": error, "supported": SUPPORTED })),
+    )
+        .into_response()
+}
+
+/// `N` of a path starting with a `/vN` segment.
+fn path_version(path: &str) -> Option<u32> {
+    let segment = path.strip_prefix("/v")?;
+    let digits = segment.split('/').next()?;
+    digits.parse().ok()
+}
+
+/// `N` of the first `application/vnd.adi.vN+json` media range in `accept`.
+fn accept_version(accept: &str) -> Option<u32> {
+    accept.split(',').find_map(|range| {
+        let media_type = range.split(';').next()?.trim();
+        media_type
+            .strip_prefix("application/vnd.adi.v")?
+            .strip_suffix("+json")?
+            .parse()
+            .ok()
+    })
+}
+
+/// `uri` without its leading `/vN` segment; `/v1` alone becomes `/`.
+fn strip_prefix(uri: &Uri) -> Uri {
+    let path = uri.path();
+    let rest = path[1..].find('/').map_or("/", |i| &path[i + 1..]);
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", rest, query),
+        None => rest.to_string(),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = PathAndQuery::try_from(path_and_query).ok();
+    Uri::from_parts(parts).unwrap_or_else(|_| uri.clone())
+}
@@ -0,0 +1,72 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Background probe of the `state.adi` lock.
+//!
+//! tokio's `RwLock` doesn't poison, so a stuck holder shows up as requests
+//! that never finish. The watchdog notices that before users do.
+
+use crate::{events, AppState};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the lock is probed.
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a read acquisition may take before the lock counts as stuck.
+pub const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+pub struct LockHealth {
+    stalled: AtomicBool,
+    /// Unix time of the last successful probe, zero before the first one
+    last_acquired_ms: AtomicU64,
+}
+
+#[derive(Serialize)]
+pub struct LockReport {
+    pub healthy: bool,
+    pub last_acquired_ms: Option<u64>,
+}
+
+impl LockHealth {
+    pub fn report(&self) -> LockReport {
+        let last = self.last_acquired_ms.load(Ordering::Relaxed);
+        LockReport {
+            healthy: !self.stalled.load(Ordering::Relaxed),
+            last_acquired_ms: (last > 0).then_some(last),
+        }
+    }
+
+    fn record(&self, acquired: bool) {
+        if acquired {
+            self.last_acquired_ms
+                .store(events::now_ms(), Ordering::Relaxed);
+            if self.stalled.swap(false, Ordering::Relaxed) {
+                tracing::info!("Index lock acquired again");
+            }
+        } else if !self.stalled.swap(true, Ordering::Relaxed) {
+            tracing::error!(
+                "Index lock not acquired within {}s, a holder may be stuck",
+                ACQUIRE_TIMEOUT.as_secs()
+            );
+        }
+    }
+}
+
+/// Probes the lock for as long as the process runs.
+pub fn spawn(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PROBE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let acquired = tokio::time::timeout(ACQUIRE_TIMEOUT, state.adi.read())
+                .await
+                .is_ok();
+            state.lock_health.record(acquired);
+        }
+    });
+}
@@ -0,0 +1,99 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+//! Reindexing on file changes under `WATCH=1`, with bursts of saves
+//! coalesced into a single run.
+
+use crate::stats::INDEX_DIR_NAME;
+use crate::{indexing, AppState};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Directories whose changes never trigger a reindex.
+const IGNORED_DIRS: [&str; 3] = [INDEX_DIR_NAME, indexing::BACKUP_DIR_NAME, ".git"];
+
+/// Changed paths waiting for the next reindex.
+#[derive(Default)]
+pub struct ChangeQueue {
+    pending: Mutex<HashSet<PathBuf>>,
+    changed: Notify,
+}
+
+impl ChangeQueue {
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    fn push(&self, paths: impl IntoIterator<Item = PathBuf>) {
+        let mut pending = self.pending.lock().unwrap();
+        let before = pending.len();
+        pending.extend(paths);
+        if pending.len() > before {
+            self.changed.notify_one();
+        }
+    }
+
+    fn take(&self) -> HashSet<PathBuf> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
+
+/// Watches the project and reindexes once changes stop arriving for
+/// `WATCH_DEBOUNCE_MS`. Changes made during a run queue the next one.
+pub fn spawn(state: Arc<AppState>) -> notify::Result<()> {
+    let root = state.project_path();
+    let queue = state.clone();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) if is_change(&event.kind) => {
+                queue
+                    .changes
+                    .push(event.paths.into_iter().filter(|p| !is_ignored(p)));
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("File watcher error: {}", e),
+        })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+    tracing::info!("Watching {} for changes", root.display());
+
+    tokio::spawn(async move {
+        // Dropping the watcher stops the events
+        let _watcher = watcher;
+        let window = state.config.watch_debounce;
+        loop {
+            state.changes.changed.notified().await;
+            // Wait for a full window without further changes
+            while tokio::time::timeout(window, state.changes.changed.notified())
+                .await
+                .is_ok()
+            {}
+
+            let changed = state.changes.take();
+            if changed.is_empty() {
+                continue;
+            }
+            tracing::info!("{} paths changed, reindexing", changed.len());
+            if let Err(e) = indexing::run(&state).await {
+                tracing::error!("Reindex after file changes failed: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn is_change(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    )
+}
+
+fn is_ignored(path: &Path) -> bool {
+    path.components()
+        .any(|c| IGNORED_DIRS.iter().any(|dir| c.as_os_str() == *dir))
+}
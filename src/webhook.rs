@@ -0,0 +1,91 @@
+// Copyright (c) 2024-2025 Ihor
+// SPDX-License-Identifier: BSL-1.1
+// See LICENSE file for details
+
+use crate::git;
+use std::path::Path;
+use std::time::Duration;
+
+const MAX_ATTEMPTS: u32 = 4;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Notifies `WEBHOOK_URL` about finished index runs.
+pub struct Webhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Webhook {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Delivers in the background so the index response isn't held up.
+    pub fn index_completed(
+        &self,
+        project_path: &Path,
+        outcome: &anyhow::Result<adi_core::IndexProgress>,
+        duration: Duration,
+    ) {
+        let mut payload = match outcome {
+            Ok(progress) => serde_json::json!({
+                "status": "success",
+                "files": progress.files_processed,
+                "symbols": progress.symbols_indexed,
+            }),
+            Err(e) => serde_json::json!({
+                "status": "failure",
+                "error": e.to_string(),
+            }),
+        };
+        payload["duration_ms"] = (duration.as_millis() as u64).into();
+
+        let url = self.url.clone();
+        let client = self.client.clone();
+        let project_path = project_path.to_path_buf();
+
+        tokio::spawn(async move {
+            payload["commit"] = git::head_commit(&project_path).await.into();
+            deliver(&client, &url, &payload).await;
+        });
+    }
+}
+
+async fn deliver(client: &reqwest::Client, url: &str, payload: &serde_json::Value) {
+    let mut backoff = INITIAL_BACKOFF;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let result = client
+            .post(url)
+            .json(payload)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+
+        match result {
+            Ok(_) => {
+                tracing::info!("Webhook delivered to {} (attempt {})", url, attempt);
+                return;
+            }
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                tracing::warn!("Webhook attempt {} to {} failed: {}", attempt, url, e);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Webhook to {} failed after {} attempts: {}",
+                    url,
+                    attempt,
+                    e
+                );
+            }
+        }
+    }
+}